@@ -0,0 +1,138 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsmErrorKind {
+    UnknownMnemonic(String),
+    MissingArgument {
+        mnemonic: String,
+    },
+    UnexpectedArgument {
+        mnemonic: String,
+    },
+    InvalidNumber(String),
+    InvalidLabel(String),
+    UndefinedLabel(String),
+    DuplicateLabel {
+        label: String,
+        previous: usize,
+    },
+    UnknownDirective(String),
+    UnterminatedMacro(String),
+    WrongArgumentCount {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    RecursiveMacro(String),
+    InvalidString(String),
+    Include {
+        path: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for AsmErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsmErrorKind::UnknownMnemonic(mnemonic) => write!(f, "Unknown mnemonic {:?}", mnemonic),
+            AsmErrorKind::MissingArgument { mnemonic } => write!(f, "{} needs an argument", mnemonic),
+            AsmErrorKind::UnexpectedArgument { mnemonic } => write!(f, "{} was given too many arguments", mnemonic),
+            AsmErrorKind::InvalidNumber(number) => write!(f, "{:?} is not a number", number),
+            AsmErrorKind::InvalidLabel(label) => write!(f, "{:?} is not a label", label),
+            AsmErrorKind::UndefinedLabel(label) => write!(f, "Label {} is never defined", label),
+            AsmErrorKind::DuplicateLabel { label, .. } => write!(f, "Label {} is defined again", label),
+            AsmErrorKind::UnknownDirective(directive) => write!(f, "Unknown directive {}", directive),
+            AsmErrorKind::UnterminatedMacro(name) => write!(f, "Macro {} has no .endm", name),
+            AsmErrorKind::WrongArgumentCount { name, expected, found } => write!(f, "Macro {} takes {} arguments but was given {}", name, expected, found),
+            AsmErrorKind::RecursiveMacro(name) => write!(f, "Macro {} expands into itself", name),
+            AsmErrorKind::InvalidString(string) => write!(f, "{} is not a string", string),
+            AsmErrorKind::Include { path, message } => write!(f, "Could not include {}: {}", path, message),
+        }
+    }
+}
+
+/// A line of assembly source, and the file it is in if it was read from one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Origin {
+    pub file: Option<String>,
+    /// Starting at 1.
+    pub line: usize,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}", self.line)?;
+
+        if let Some(file) = &self.file {
+            write!(f, " of {}", file)?;
+        };
+
+        Ok(())
+    }
+}
+
+/// An error in assembly source, along with the line it is on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    kind: AsmErrorKind,
+
+    origin: Origin,
+    call_sites: Vec<Origin>,
+}
+
+impl AsmError {
+    pub fn new(kind: AsmErrorKind, line: usize) -> Self {
+        Self::at(kind, Origin {
+            file: None,
+            line,
+        }, vec![])
+    }
+
+    /// An error on a line that came out of the macro calls at `call_sites`,
+    /// innermost first.
+    pub fn at(kind: AsmErrorKind, origin: Origin, call_sites: Vec<Origin>) -> Self {
+        Self {
+            kind,
+
+            origin,
+            call_sites,
+        }
+    }
+
+    pub fn kind(&self) -> &AsmErrorKind {
+        &self.kind
+    }
+
+    /// The line the error is on, starting at 1.
+    pub fn line(&self) -> usize {
+        self.origin.line
+    }
+
+    pub fn origin(&self) -> &Origin {
+        &self.origin
+    }
+
+    /// The macro calls that produced the line the error is on, innermost
+    /// first. Empty if the line was written out in the source.
+    pub fn call_sites(&self) -> &[Origin] {
+        &self.call_sites
+    }
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} on {}", self.kind, self.origin)?;
+
+        if let AsmErrorKind::DuplicateLabel { previous, .. } = &self.kind {
+            write!(f, " (first defined on line {})", previous)?;
+        };
+
+        for call_site in &self.call_sites {
+            write!(f, ", in a macro called on {}", call_site)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for AsmError {}
@@ -0,0 +1,370 @@
+use super::asm_error::{AsmError, AsmErrorKind};
+use super::syntax::Syntax;
+use crate::vm::extensions::{self, Extensions};
+use crate::vm::parser::encoder;
+use crate::vm::parser::token::{CommandType, ImpType, Opcode};
+
+use std::collections::{HashMap, HashSet};
+
+/// What follows a mnemonic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Argument {
+    None,
+    Number,
+    Label,
+}
+
+impl Argument {
+    fn of(command: &CommandType) -> Self {
+        match command {
+            CommandType::Push(_) | CommandType::Copy(_) | CommandType::Slide(_) => Argument::Number,
+            CommandType::Labl(_) | CommandType::Call(_) | CommandType::Jump(_) | CommandType::JumpZ(_) | CommandType::JumpN(_) => Argument::Label,
+
+            _ => Argument::None,
+        }
+    }
+}
+
+/// One line of assembly that holds an instruction.
+struct Statement<'a> {
+    line: usize,
+
+    mnemonic: &'a str,
+    argument: Option<&'a str>,
+}
+
+/// Turns line-oriented mnemonics such as `push 5` or `jz end` into commands.
+///
+/// Each line holds at most one instruction, and everything after a `;` or `#`
+/// is a comment. Labels have names, which are turned into strings of spaces
+/// and tabs in the order they first appear. A label can also be given as its
+/// exact spaces and tabs by writing them as `S` and `T` after a `%`, so `%`
+/// on its own is the empty label. An instruction that is not part of the
+/// language can be written the same way, IMP included, such as `%TLLS`.
+#[derive(Debug, Clone, Default)]
+pub struct Assembler {
+    syntax: Syntax,
+    extensions: Extensions,
+}
+
+impl Assembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads mnemonics, labels and numbers the way `syntax` writes them.
+    pub fn with_syntax(mut self, syntax: Syntax) -> Self {
+        self.syntax = syntax;
+
+        self
+    }
+
+    /// Allows the names of `extensions` to be used as mnemonics.
+    pub fn with_extensions(mut self, extensions: &Extensions) -> Self {
+        self.extensions = extensions.clone();
+
+        self
+    }
+
+    pub fn assemble(&self, source: &str) -> Result<Vec<CommandType>, AsmError> {
+        let hidden;
+        let mut statements = statements(source);
+
+        if self.syntax == Syntax::Wsa {
+            hidden = hidden_labels(&statements);
+            statements = statements.into_iter()
+                .map(|statement| expand_wsa(statement, &hidden))
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .flatten()
+                .collect();
+        };
+
+        let labels = self.name_labels(&statements)?;
+
+        let mut commands = Vec::with_capacity(statements.len());
+
+        let mut defined = HashMap::<String, usize>::new();
+        let mut references = vec![];
+
+        for statement in &statements {
+            let command = self.command(statement, &labels)?;
+            let name = statement.argument.unwrap_or_default();
+
+            match &command {
+                CommandType::Labl(label) => {
+                    if let Some(&previous) = defined.get(label) {
+                        return Err(AsmError::new(AsmErrorKind::DuplicateLabel {
+                            label: name.to_string(),
+                            previous,
+                        }, statement.line));
+                    };
+
+                    defined.insert(label.clone(), statement.line);
+                },
+
+                CommandType::Call(label) | CommandType::Jump(label) | CommandType::JumpZ(label) | CommandType::JumpN(label) => {
+                    references.push((label.clone(), name, statement.line));
+                },
+
+                _ => {},
+            };
+
+            commands.push(command);
+        }
+
+        if let Some((_, name, line)) = references.into_iter().find(|(label, _, _)| !defined.contains_key(label)) {
+            return Err(AsmError::new(AsmErrorKind::UndefinedLabel(name.to_string()), line));
+        };
+
+        Ok(commands)
+    }
+
+    /// Assembles `source` into Whitespace source that the parser accepts.
+    pub fn assemble_to_whitespace(&self, source: &str) -> Result<String, AsmError> {
+        Ok(self.assemble(source)?.iter().map(encoder::encode).collect())
+    }
+
+    /// The command a statement stands for, with placeholder arguments, after
+    /// checking that it has an argument exactly when it needs one.
+    fn template(&self, statement: &Statement) -> Result<CommandType, AsmError> {
+        let template = match self.syntax.command(statement.mnemonic) {
+            Some(command) => command.clone(),
+
+            None => match self.extensions.iter().find(|extension| extension.name == self.syntax.extension_name(statement.mnemonic)) {
+                Some(extension) => CommandType::Extension(extension.opcode.clone()),
+
+                None => match raw_opcode(statement.mnemonic) {
+                    Some(opcode) => CommandType::Extension(opcode),
+                    None => return Err(AsmError::new(AsmErrorKind::UnknownMnemonic(statement.mnemonic.to_string()), statement.line)),
+                },
+            },
+        };
+
+        let argument = Argument::of(&template);
+        let mnemonic = statement.mnemonic.to_string();
+
+        match statement.argument {
+            Some(_) if argument == Argument::None => Err(AsmError::new(AsmErrorKind::UnexpectedArgument { mnemonic }, statement.line)),
+            Some(text) if text.contains(char::is_whitespace) && !text.starts_with('\'') => Err(AsmError::new(AsmErrorKind::UnexpectedArgument { mnemonic }, statement.line)),
+            None if argument != Argument::None => Err(AsmError::new(AsmErrorKind::MissingArgument { mnemonic }, statement.line)),
+
+            _ => Ok(template),
+        }
+    }
+
+    fn command(&self, statement: &Statement, labels: &HashMap<&str, String>) -> Result<CommandType, AsmError> {
+        let template = self.template(statement)?;
+
+        let argument = statement.argument.unwrap_or_default();
+
+        let number = || self.syntax.number(argument)
+            .ok_or_else(|| AsmError::new(AsmErrorKind::InvalidNumber(argument.to_string()), statement.line));
+
+        let label = || labels[argument].clone();
+
+        Ok(match template {
+            CommandType::Push(_) => CommandType::Push(number()?),
+            CommandType::Copy(_) => CommandType::Copy(number()?),
+            CommandType::Slide(_) => CommandType::Slide(number()?),
+
+            CommandType::Labl(_) => CommandType::Labl(label()),
+            CommandType::Call(_) => CommandType::Call(label()),
+            CommandType::Jump(_) => CommandType::Jump(label()),
+            CommandType::JumpZ(_) => CommandType::JumpZ(label()),
+            CommandType::JumpN(_) => CommandType::JumpN(label()),
+
+            command => command,
+        })
+    }
+
+    /// Maps every label argument to the spaces and tabs it stands for. Names
+    /// are numbered in binary, skipping any numbers that were also written out
+    /// with `%`.
+    fn name_labels<'a>(&self, statements: &[Statement<'a>]) -> Result<HashMap<&'a str, String>, AsmError> {
+        let arguments: Vec<(&'a str, usize)> = statements.iter()
+            .filter(|statement| self.syntax.command(statement.mnemonic).is_some_and(|command| Argument::of(command) == Argument::Label))
+            .filter_map(|statement| statement.argument.map(|argument| (argument, statement.line)))
+            .collect();
+
+        let mut labels = HashMap::new();
+        let mut taken = HashSet::new();
+
+        for &(argument, line) in &arguments {
+            let raw = match argument.strip_prefix('%') {
+                Some(raw) => raw,
+                None if self.syntax.has_raw_labels() => argument,
+                None => continue,
+            };
+
+            if !raw.chars().all(|c| c == 'S' || c == 'T') {
+                return Err(AsmError::new(AsmErrorKind::InvalidLabel(argument.to_string()), line));
+            };
+
+            let label: String = raw.chars().map(|c| if c == 'S' {' '} else {'\t'}).collect();
+
+            taken.insert(label.clone());
+            labels.insert(argument, label);
+        }
+
+        let mut next = 1u64;
+
+        for &(argument, _) in &arguments {
+            if labels.contains_key(argument) {
+                continue;
+            };
+
+            let label = loop {
+                let label = numbered_label(next);
+
+                next += 1;
+
+                if !taken.contains(&label) {
+                    break label;
+                };
+            };
+
+            labels.insert(argument, label);
+        }
+
+        Ok(labels)
+    }
+}
+
+/// Assembles `source` with the standard mnemonics into Whitespace source.
+pub fn assemble(source: &str) -> Result<String, AsmError> {
+    Assembler::new().assemble_to_whitespace(source)
+}
+
+fn statements(source: &str) -> Vec<Statement<'_>> {
+    source.lines().enumerate().filter_map(|(index, line)| {
+        let code = strip_comment(line).trim();
+
+        let (mnemonic, argument) = match code.split_once(char::is_whitespace) {
+            Some((mnemonic, argument)) => (mnemonic, Some(argument.trim())),
+            None => (code, None),
+        };
+
+        if mnemonic.is_empty() {
+            return None;
+        };
+
+        Some(Statement {
+            line: index + 1,
+
+            mnemonic,
+            argument,
+        })
+    }).collect()
+}
+
+/// The names of the labels that the conditional jumps of `wsa` jump over,
+/// two for each line that needs them. They hold a `;`, which starts a
+/// comment, so no label in the source can have the same name.
+fn hidden_labels(statements: &[Statement]) -> HashMap<usize, [String; 2]> {
+    statements.iter()
+        .filter(|statement| WSA_JUMPS.iter().any(|jump| jump.eq_ignore_ascii_case(statement.mnemonic)))
+        .map(|statement| (statement.line, [format!("{};{}", statement.mnemonic, statement.line), format!("{};{};end", statement.mnemonic, statement.line)]))
+        .collect()
+}
+
+/// The conditional jumps that `wsa` has on top of `jumpz` and `jumpn`.
+const WSA_JUMPS: [&str; 7] = ["jumpp", "jumpnp", "jumppn", "jumpnz", "jumpzn", "jumppz", "jumpzp"];
+
+/// The plain statements a `wsa` pseudo-instruction stands for, or the
+/// statement itself if it is not one. Arithmetic and heap access can take
+/// the number to work on, which is pushed first, and the conditional jumps
+/// are made out of `jumpz` and `jumpn` without changing what is on the stack
+/// below the value they test.
+fn expand_wsa<'a>(statement: Statement<'a>, hidden: &'a HashMap<usize, [String; 2]>) -> Result<Vec<Statement<'a>>, AsmError> {
+    let line = statement.line;
+    let argument = statement.argument;
+
+    if argument.is_none() && hidden.contains_key(&line) {
+        return Err(AsmError::new(AsmErrorKind::MissingArgument { mnemonic: statement.mnemonic.to_string() }, line));
+    };
+
+    let at = |mnemonic, argument| Statement {
+        line,
+
+        mnemonic,
+        argument,
+    };
+
+    let hide = |index: usize| hidden.get(&line).map(|names| names[index].as_str());
+
+    Ok(match (statement.mnemonic.to_ascii_lowercase().as_str(), argument) {
+        ("add" | "sub" | "mul" | "div" | "mod" | "retrive" | "retrieve", Some(_)) => vec![at("push", argument), at(statement.mnemonic, None)],
+        ("store", Some(_)) => vec![at("push", argument), at("swap", None), at("store", None)],
+
+        // Positive
+        ("jumpp", _) => vec![
+            at("doub", None), at("jumpn", hide(0)), at("doub", None), at("jumpz", hide(0)),
+            at("pop", None), at("jump", argument),
+            at("label", hide(0)), at("pop", None),
+        ],
+
+        // Not zero
+        ("jumpnp" | "jumppn", _) => vec![at("jumpz", hide(0)), at("jump", argument), at("label", hide(0))],
+
+        // Not positive
+        ("jumpnz" | "jumpzn", _) => vec![
+            at("doub", None), at("jumpn", hide(0)), at("jumpz", argument), at("jump", hide(1)),
+            at("label", hide(0)), at("pop", None), at("jump", argument),
+            at("label", hide(1)),
+        ],
+
+        // Not negative
+        ("jumppz" | "jumpzp", _) => vec![
+            at("doub", None), at("jumpn", hide(0)), at("pop", None), at("jump", argument),
+            at("label", hide(0)), at("pop", None),
+        ],
+
+        _ => vec![statement],
+    })
+}
+
+/// An instruction outside the language written as `%` and the `S`, `T` and
+/// `L` of its IMP and command. `None` if it is not one, or if it overlaps a
+/// standard command.
+fn raw_opcode(text: &str) -> Option<Opcode> {
+    let symbols: String = text.strip_prefix('%')?.chars()
+        .map(|c| match c {
+            'S' => Some(' '),
+            'T' => Some('\t'),
+            'L' => Some('\n'),
+
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+
+    let imp = [ImpType::Stack, ImpType::Arithmetic, ImpType::Heap, ImpType::Flow, ImpType::IO].into_iter()
+        .find(|imp| symbols.starts_with(encoder::encode_imp(*imp)))?;
+
+    let opcode = Opcode::new(imp, &symbols[encoder::encode_imp(imp).len()..]);
+
+    (!opcode.pattern.is_empty() && !extensions::overlaps_standard(&opcode)).then_some(opcode)
+}
+
+/// The spaces and tabs of `number` in binary, which is how named labels are
+/// turned into real ones.
+pub(super) fn numbered_label(number: u64) -> String {
+    format!("{:b}", number).chars().map(|c| if c == '0' {' '} else {'\t'}).collect()
+}
+
+/// Cuts a line off at the first `;` or `#` that is not in quotes.
+pub(super) fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+
+    for (index, c) in line.char_indices() {
+        match (c, quote) {
+            ('\'' | '"', None) => quote = Some(c),
+            (_, Some(open)) if c == open => quote = None,
+            (';' | '#', None) => return &line[..index],
+
+            _ => {},
+        };
+    }
+
+    line
+}
@@ -0,0 +1,221 @@
+use super::assembler::numbered_label;
+use crate::vm::number::Value;
+use crate::vm::parser::encoder;
+use crate::vm::parser::parser_error::ParserError;
+use crate::vm::parser::token::{CommandType, Location, Opcode, Token, TokenType};
+use crate::vm::program::Program;
+
+use std::collections::HashMap;
+
+/// Builds a program one instruction at a time from Rust code.
+///
+/// Labels have names, which are turned into strings of spaces and tabs in the
+/// order they first appear, the same way [`Assembler`](super::assembler::Assembler)
+/// numbers them. Every method returns the builder, so calls can be chained:
+///
+/// ```
+/// use whitespace::ProgramBuilder;
+///
+/// let mut builder = ProgramBuilder::new();
+///
+/// builder.push(3)
+///     .label("loop").dup().jump_if_zero("end")
+///     .dup().out_int().push(1).sub().jump("loop")
+///     .label("end").end();
+///
+/// assert!(builder.build().is_ok());
+/// assert!(builder.to_whitespace().starts_with("   \t\t\n"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ProgramBuilder {
+    commands: Vec<CommandType>,
+    labels: HashMap<String, String>,
+}
+
+impl ProgramBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends any command as it is. Its labels are taken to already be
+    /// spaces and tabs, and may clash with the ones given to names.
+    pub fn command(&mut self, command: CommandType) -> &mut Self {
+        self.commands.push(command);
+
+        self
+    }
+
+    pub fn push(&mut self, n: impl Into<Value>) -> &mut Self {
+        self.command(CommandType::Push(n.into()))
+    }
+
+    pub fn dup(&mut self) -> &mut Self {
+        self.command(CommandType::Dup)
+    }
+
+    pub fn copy(&mut self, n: impl Into<Value>) -> &mut Self {
+        self.command(CommandType::Copy(n.into()))
+    }
+
+    pub fn swap(&mut self) -> &mut Self {
+        self.command(CommandType::Swap)
+    }
+
+    pub fn discard(&mut self) -> &mut Self {
+        self.command(CommandType::Disc)
+    }
+
+    pub fn slide(&mut self, n: impl Into<Value>) -> &mut Self {
+        self.command(CommandType::Slide(n.into()))
+    }
+
+    pub fn add(&mut self) -> &mut Self {
+        self.command(CommandType::Add)
+    }
+
+    pub fn sub(&mut self) -> &mut Self {
+        self.command(CommandType::Sub)
+    }
+
+    pub fn mul(&mut self) -> &mut Self {
+        self.command(CommandType::Mult)
+    }
+
+    pub fn div(&mut self) -> &mut Self {
+        self.command(CommandType::IDiv)
+    }
+
+    pub fn modulo(&mut self) -> &mut Self {
+        self.command(CommandType::Mod)
+    }
+
+    pub fn store(&mut self) -> &mut Self {
+        self.command(CommandType::Store)
+    }
+
+    pub fn retrieve(&mut self) -> &mut Self {
+        self.command(CommandType::Retr)
+    }
+
+    pub fn label(&mut self, name: &str) -> &mut Self {
+        let label = self.name(name);
+
+        self.command(CommandType::Labl(label))
+    }
+
+    pub fn call(&mut self, name: &str) -> &mut Self {
+        let label = self.name(name);
+
+        self.command(CommandType::Call(label))
+    }
+
+    pub fn jump(&mut self, name: &str) -> &mut Self {
+        let label = self.name(name);
+
+        self.command(CommandType::Jump(label))
+    }
+
+    pub fn jump_if_zero(&mut self, name: &str) -> &mut Self {
+        let label = self.name(name);
+
+        self.command(CommandType::JumpZ(label))
+    }
+
+    pub fn jump_if_negative(&mut self, name: &str) -> &mut Self {
+        let label = self.name(name);
+
+        self.command(CommandType::JumpN(label))
+    }
+
+    pub fn ret(&mut self) -> &mut Self {
+        self.command(CommandType::EndS)
+    }
+
+    pub fn end(&mut self) -> &mut Self {
+        self.command(CommandType::EndP)
+    }
+
+    pub fn out_char(&mut self) -> &mut Self {
+        self.command(CommandType::OutC)
+    }
+
+    pub fn out_int(&mut self) -> &mut Self {
+        self.command(CommandType::OutI)
+    }
+
+    pub fn read_char(&mut self) -> &mut Self {
+        self.command(CommandType::ReadC)
+    }
+
+    pub fn read_int(&mut self) -> &mut Self {
+        self.command(CommandType::ReadI)
+    }
+
+    pub fn extension(&mut self, opcode: Opcode) -> &mut Self {
+        self.command(CommandType::Extension(opcode))
+    }
+
+    /// The commands so far, with names already turned into labels.
+    pub fn commands(&self) -> &[CommandType] {
+        &self.commands
+    }
+
+    /// The spaces and tabs that `name` was turned into, if it has been used.
+    pub fn label_of(&self, name: &str) -> Option<&str> {
+        self.labels.get(name).map(String::as_str)
+    }
+
+    /// Writes the commands out as Whitespace source.
+    pub fn to_whitespace(&self) -> String {
+        self.commands.iter().map(encoder::encode).collect()
+    }
+
+    /// Links the commands into a program, with locations pointing into the
+    /// source from [`ProgramBuilder::to_whitespace`].
+    ///
+    /// Fails on a jump to a label that is never defined, or on a label that is
+    /// defined more than once.
+    pub fn build(&self) -> Result<Program, ParserError> {
+        let mut tokens = Vec::with_capacity(self.commands.len());
+
+        let mut start = 0;
+        let mut line = 1;
+        let mut column = 1;
+
+        for command in &self.commands {
+            let source = encoder::encode(command);
+
+            tokens.push(Token {
+                token_type: TokenType::Command(command.clone()),
+
+                location: Location {
+                    start,
+                    end: start + source.len(),
+
+                    line,
+                    column,
+                },
+            });
+
+            start += source.len();
+
+            for c in source.chars() {
+                if c == '\n' {
+                    line += 1;
+                    column = 1;
+                } else {
+                    column += 1;
+                };
+            }
+        }
+
+        Program::link(tokens)
+    }
+
+    /// The label `name` stands for, giving it the next free one if it is new.
+    fn name(&mut self, name: &str) -> String {
+        let next = self.labels.len() as u64 + 1;
+
+        self.labels.entry(name.to_string()).or_insert_with(|| numbered_label(next)).clone()
+    }
+}
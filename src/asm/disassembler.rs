@@ -0,0 +1,176 @@
+use super::assembler::numbered_label;
+use super::syntax::Syntax;
+use crate::vm::extensions::Extensions;
+use crate::vm::parser::encoder;
+use crate::vm::parser::notation::Notation;
+use crate::vm::parser::token::CommandType;
+use crate::vm::program::{Instruction, Program};
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+/// Turns a program back into the mnemonics read by
+/// [`Assembler`](super::assembler::Assembler).
+///
+/// Each instruction gets a line of its own, followed by a comment with its
+/// index and the line it came from. Labels are named `label_0`, `label_1` and
+/// so on in the order they are defined, and the comment also shows their
+/// spaces and tabs as `S` and `T`. A label is written as its spaces and tabs
+/// instead, with its name in the comment, wherever the assembler would not
+/// turn the name back into the same ones, and always in syntaxes that write
+/// labels that way. Extensions that are not registered are written as their
+/// `S`, `T` and `L`. Assembling the output gives back the exact program.
+#[derive(Debug, Clone, Default)]
+pub struct Disassembler {
+    syntax: Syntax,
+    extensions: Extensions,
+}
+
+impl Disassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Writes mnemonics and labels the way `syntax` does.
+    pub fn with_syntax(mut self, syntax: Syntax) -> Self {
+        self.syntax = syntax;
+
+        self
+    }
+
+    /// Shows extension instructions by the names they are registered under.
+    pub fn with_extensions(mut self, extensions: &Extensions) -> Self {
+        self.extensions = extensions.clone();
+
+        self
+    }
+
+    pub fn disassemble(&self, program: &Program) -> String {
+        let instructions = program.instructions();
+
+        let names: HashMap<usize, String> = instructions.iter().enumerate()
+            .filter(|(_, instruction)| matches!(instruction, Instruction::Labl(_)))
+            .enumerate()
+            .map(|(count, (index, _))| (index, format!("label_{}", count)))
+            .collect();
+
+        let raw = self.raw_labels(program);
+
+        let mut output = String::new();
+
+        for (index, instruction) in instructions.iter().enumerate() {
+            let target = match instruction {
+                Instruction::Labl(_) => Some(index),
+                Instruction::Call(target) | Instruction::Jump(target) | Instruction::JumpZ(target) | Instruction::JumpN(target) => Some(*target),
+
+                _ => None,
+            };
+
+            let label = target.map(|target| match &instructions[target] {
+                Instruction::Labl(label) if raw.contains(label.as_str()) && self.syntax.has_raw_labels() && !label.is_empty() => raw_label(label)[1..].to_string(),
+                Instruction::Labl(label) if raw.contains(label.as_str()) => raw_label(label),
+                _ => names[&target].clone(),
+            });
+
+            let command = program.command(index).expect("index should be in range");
+
+            let mnemonic = match (self.syntax.mnemonic(&command), &command) {
+                (Some(mnemonic), _) => mnemonic.to_string(),
+
+                (None, CommandType::Extension(opcode)) => match self.extensions.iter().find(|extension| &extension.opcode == opcode) {
+                    Some(extension) => extension.name.clone(),
+                    None => format!("%{}", Notation::Stl.render(&encoder::encode(&command))),
+                },
+
+                (None, _) => unreachable!("every syntax has a mnemonic for each command"),
+            };
+
+            let code = match (&command, label) {
+                (CommandType::Labl(_), Some(label)) => format!("{} {}", mnemonic, label),
+                (_, Some(label)) => format!("    {} {}", mnemonic, label),
+                (CommandType::Push(n) | CommandType::Copy(n) | CommandType::Slide(n), _) => format!("    {} {}", mnemonic, n),
+
+                _ => format!("    {}", mnemonic),
+            };
+
+            write!(output, "{:<28}; {}", code, index).unwrap();
+
+            if let Some(location) = program.location(index) {
+                write!(output, " (line {})", location.line).unwrap();
+            };
+
+            if let Some(target) = target {
+                match &instructions[target] {
+                    Instruction::Labl(label) if raw.contains(label.as_str()) => write!(output, " {}", names[&target]).unwrap(),
+                    Instruction::Labl(label) => write!(output, " {}", raw_label(label)).unwrap(),
+
+                    _ => unreachable!("jump targets are labels"),
+                };
+            };
+
+            output.push('\n');
+        }
+
+        output
+    }
+
+    /// The labels that have to be written as their spaces and tabs. Names are
+    /// numbered in the order they first appear, skipping the labels that are
+    /// written out, so a label can keep its name only if it gets the next
+    /// number.
+    fn raw_labels<'a>(&self, program: &'a Program) -> HashSet<&'a str> {
+        let instructions = program.instructions();
+
+        let mut raw = HashSet::new();
+        let mut seen = HashSet::new();
+
+        let mut next = 1u64;
+
+        for instruction in instructions {
+            let target = match instruction {
+                Instruction::Labl(label) => label,
+                Instruction::Call(target) | Instruction::Jump(target) | Instruction::JumpZ(target) | Instruction::JumpN(target) => match &instructions[*target] {
+                    Instruction::Labl(label) => label,
+                    _ => unreachable!("jump targets are labels"),
+                },
+
+                _ => continue,
+            };
+
+            if !seen.insert(target.as_str()) {
+                continue;
+            };
+
+            if self.syntax.has_raw_labels() {
+                raw.insert(target.as_str());
+
+                continue;
+            };
+
+            while raw.contains(numbered_label(next).as_str()) {
+                next += 1;
+            }
+
+            if *target == numbered_label(next) {
+                next += 1;
+            } else {
+                raw.insert(target.as_str());
+            };
+        }
+
+        raw
+    }
+}
+
+/// Disassembles `program` with the standard mnemonics.
+pub fn disassemble(program: &Program) -> String {
+    Disassembler::new().disassemble(program)
+}
+
+/// A label's spaces and tabs as `S` and `T` after a `%`, which every syntax
+/// accepts.
+fn raw_label(label: &str) -> String {
+    let bits: String = label.chars().map(|c| if c == ' ' {'S'} else {'T'}).collect();
+
+    format!("%{}", bits)
+}
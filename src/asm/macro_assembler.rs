@@ -0,0 +1,520 @@
+use super::asm_error::{AsmError, AsmErrorKind, Origin};
+use super::assembler::{self, numbered_label, Assembler};
+use super::syntax::Syntax;
+use crate::vm::extensions::Extensions;
+use crate::vm::number::Value;
+use crate::vm::parser::token::CommandType;
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone)]
+struct Macro {
+    parameters: Vec<String>,
+    body: Vec<(String, Origin)>,
+}
+
+/// A line of plain assembly that the source expanded into.
+struct Line {
+    text: String,
+
+    origin: Origin,
+    call_sites: Vec<Origin>,
+}
+
+/// Assembles source that uses directives on top of the mnemonics of an
+/// [`Assembler`]:
+///
+/// - `.const NAME value` replaces the word `NAME` with `value` on every later
+///   line.
+/// - `.macro name a, b` up to `.endm` defines a macro, which is called as
+///   `name 1, 2` and has its parameters replaced by the arguments. Labels that
+///   start with `@` are local to each call of the macro they are used in. In a
+///   syntax with raw labels they are given spaces and tabs that no other
+///   label uses.
+/// - `.include "file"` assembles another file in its place. The path is
+///   relative to the including file.
+/// - `.string "text"` prints the text with `push` and `outc`, while
+///   `.string address, "text"` stores it in the heap from `address` on,
+///   followed by a 0.
+///
+/// Errors on a line that came from a macro point at the line inside the macro
+/// and at every call that led to it.
+#[derive(Debug, Clone, Default)]
+pub struct MacroAssembler {
+    assembler: Assembler,
+    syntax: Syntax,
+}
+
+impl MacroAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads mnemonics, labels and numbers the way `syntax` writes them.
+    pub fn with_syntax(mut self, syntax: Syntax) -> Self {
+        self.assembler = self.assembler.with_syntax(syntax);
+        self.syntax = syntax;
+
+        self
+    }
+
+    /// Allows the names of `extensions` to be used as mnemonics.
+    pub fn with_extensions(mut self, extensions: &Extensions) -> Self {
+        self.assembler = self.assembler.with_extensions(extensions);
+
+        self
+    }
+
+    /// Files included by `source` are found relative to the working
+    /// directory.
+    pub fn assemble(&self, source: &str) -> Result<Vec<CommandType>, AsmError> {
+        self.assemble_from(source, None)
+    }
+
+    /// Assembles `source` that was read from the file at `path`. Errors name
+    /// the file, and files it includes are found relative to it.
+    pub fn assemble_file(&self, source: &str, path: impl AsRef<Path>) -> Result<Vec<CommandType>, AsmError> {
+        self.assemble_from(source, Some(path.as_ref()))
+    }
+
+    fn assemble_from(&self, source: &str, file: Option<&Path>) -> Result<Vec<CommandType>, AsmError> {
+        let mut expansion = Expansion {
+            syntax: self.syntax,
+
+            constants: HashMap::new(),
+            macros: HashMap::new(),
+            includes: file.map(Path::to_path_buf).into_iter().collect(),
+            active: vec![],
+            calls: 0,
+
+            lines: vec![],
+        };
+
+        expansion.source(source, file)?;
+
+        let mut lines = expansion.lines;
+
+        if self.syntax.has_raw_labels() {
+            raw_local_labels(&mut lines);
+        };
+        let text: Vec<&str> = lines.iter().map(|line| line.text.as_str()).collect();
+
+        self.assembler.assemble(&text.join("\n")).map_err(|error| {
+            let line = &lines[error.line() - 1];
+
+            let kind = match error.kind().clone() {
+                AsmErrorKind::DuplicateLabel { label, previous } => AsmErrorKind::DuplicateLabel {
+                    label,
+                    previous: lines[previous - 1].origin.line,
+                },
+
+                kind => kind,
+            };
+
+            AsmError::at(kind, line.origin.clone(), line.call_sites.clone())
+        })
+    }
+}
+
+/// The state of expanding the directives of a source into plain lines.
+struct Expansion {
+    syntax: Syntax,
+
+    constants: HashMap<String, String>,
+    macros: HashMap<String, Macro>,
+    /// The files being assembled, with the one that is currently read last.
+    includes: Vec<PathBuf>,
+    /// The macros being expanded, innermost last.
+    active: Vec<String>,
+    /// How many macro calls have been expanded, which keeps the local labels
+    /// of each call apart.
+    calls: usize,
+
+    lines: Vec<Line>,
+}
+
+impl Expansion {
+    fn source(&mut self, source: &str, file: Option<&Path>) -> Result<(), AsmError> {
+        let file = file.map(|file| file.display().to_string());
+
+        let mut recording: Option<(String, Macro, Origin)> = None;
+
+        for (index, text) in source.lines().enumerate() {
+            let origin = Origin {
+                file: file.clone(),
+                line: index + 1,
+            };
+
+            let (word, rest) = split_word(assembler::strip_comment(text).trim());
+
+            if let Some((_, definition, _)) = &mut recording {
+                if word == ".endm" {
+                    let (name, definition, _) = recording.take().expect("a macro is being recorded");
+
+                    self.macros.insert(name, definition);
+                } else {
+                    definition.body.push((text.to_string(), origin));
+                };
+
+                continue;
+            };
+
+            if word == ".macro" {
+                let (name, parameters) = split_word(rest);
+
+                if name.is_empty() {
+                    return Err(AsmError::at(AsmErrorKind::MissingArgument { mnemonic: word.to_string() }, origin, vec![]));
+                };
+
+                let definition = Macro {
+                    parameters: split_arguments(parameters).into_iter().map(String::from).collect(),
+                    body: vec![],
+                };
+
+                recording = Some((name.to_string(), definition, origin));
+
+                continue;
+            };
+
+            self.line(text, origin, &[])?;
+        }
+
+        if let Some((name, _, origin)) = recording {
+            return Err(AsmError::at(AsmErrorKind::UnterminatedMacro(name), origin, vec![]));
+        };
+
+        Ok(())
+    }
+
+    fn line(&mut self, text: &str, origin: Origin, call_sites: &[Origin]) -> Result<(), AsmError> {
+        let error = |kind| Err(AsmError::at(kind, origin.clone(), call_sites.to_vec()));
+
+        let code = assembler::strip_comment(text).trim();
+
+        if let (".const", definition) = split_word(code) {
+            let (name, value) = split_word(definition);
+
+            if value.is_empty() {
+                return error(AsmErrorKind::MissingArgument { mnemonic: String::from(".const") });
+            };
+
+            let value = substitute(value, |word| self.constants.get(word).cloned());
+
+            self.constants.insert(name.to_string(), value);
+
+            return Ok(());
+        };
+
+        let code = substitute(code, |word| self.constants.get(word).cloned());
+        let (word, rest) = split_word(&code);
+
+        match word {
+            "" => Ok(()),
+
+            ".include" => {
+                let Some(path) = parse_string(rest) else {
+                    return error(AsmErrorKind::InvalidString(rest.to_string()));
+                };
+
+                let path = match self.includes.last().and_then(|file| file.parent()) {
+                    Some(directory) => directory.join(path),
+                    None => PathBuf::from(path),
+                };
+
+                if self.includes.iter().any(|file| canonical(file) == canonical(&path)) {
+                    return error(AsmErrorKind::Include {
+                        path: path.display().to_string(),
+                        message: String::from("it is already being included"),
+                    });
+                };
+
+                let source = match fs::read_to_string(&path) {
+                    Ok(source) => source,
+                    Err(e) => return error(AsmErrorKind::Include {
+                        path: path.display().to_string(),
+                        message: e.to_string(),
+                    }),
+                };
+
+                self.includes.push(path.clone());
+
+                let result = self.source(&source, Some(&path));
+
+                self.includes.pop();
+
+                result
+            },
+
+            ".string" => {
+                let arguments = split_arguments(rest);
+
+                let (address, string) = match arguments.as_slice() {
+                    [] => return error(AsmErrorKind::MissingArgument { mnemonic: word.to_string() }),
+                    [string] => (None, *string),
+                    [address, string] => (Some(*address), *string),
+                    _ => return error(AsmErrorKind::UnexpectedArgument { mnemonic: word.to_string() }),
+                };
+
+                let Some(string) = parse_string(string) else {
+                    return error(AsmErrorKind::InvalidString(string.to_string()));
+                };
+
+                let address = match address.map(|address| (address, self.syntax.number(address))) {
+                    Some((_, Some(address))) => Some(address),
+                    Some((address, None)) => return error(AsmErrorKind::InvalidNumber(address.to_string())),
+                    None => None,
+                };
+
+                for command in string_commands(&string, address) {
+                    self.lines.push(Line {
+                        text: self.write(&command),
+
+                        origin: origin.clone(),
+                        call_sites: call_sites.to_vec(),
+                    });
+                }
+
+                Ok(())
+            },
+
+            _ if word.starts_with('.') => error(AsmErrorKind::UnknownDirective(word.to_string())),
+
+            _ => match self.macros.get(word).cloned() {
+                Some(definition) => {
+                    let arguments = split_arguments(rest);
+
+                    if arguments.len() != definition.parameters.len() {
+                        return error(AsmErrorKind::WrongArgumentCount {
+                            name: word.to_string(),
+                            expected: definition.parameters.len(),
+                            found: arguments.len(),
+                        });
+                    };
+
+                    if self.active.iter().any(|name| name == word) {
+                        return error(AsmErrorKind::RecursiveMacro(word.to_string()));
+                    };
+
+                    self.calls += 1;
+
+                    let call = self.calls;
+
+                    let bindings: HashMap<&str, &str> = definition.parameters.iter()
+                        .map(String::as_str)
+                        .zip(arguments)
+                        .collect();
+
+                    let mut call_sites = call_sites.to_vec();
+                    call_sites.insert(0, origin.clone());
+
+                    self.active.push(word.to_string());
+
+                    for (text, origin) in definition.body {
+                        let text = substitute(assembler::strip_comment(&text), |word| match bindings.get(word) {
+                            Some(argument) => Some(argument.to_string()),
+                            None => word.starts_with('@').then(|| format!("{}.{}", word, call)),
+                        });
+
+                        self.line(&text, origin, &call_sites)?;
+                    }
+
+                    self.active.pop();
+
+                    Ok(())
+                },
+
+                None => {
+                    self.lines.push(Line {
+                        text: code.to_string(),
+
+                        origin,
+                        call_sites: call_sites.to_vec(),
+                    });
+
+                    Ok(())
+                },
+            },
+        }
+    }
+
+    /// Writes a command that has no label out as assembly.
+    fn write(&self, command: &CommandType) -> String {
+        let mnemonic = self.syntax.mnemonic(command).expect("every syntax has a mnemonic for each command");
+
+        match command {
+            CommandType::Push(n) | CommandType::Copy(n) | CommandType::Slide(n) => format!("{} {}", mnemonic, n),
+            _ => mnemonic.to_string(),
+        }
+    }
+}
+
+/// Replaces the local labels of macro calls with spaces and tabs, for a
+/// syntax that only has those. Each gets the next numbered label that is not
+/// written anywhere else in `lines`.
+fn raw_local_labels(lines: &mut [Line]) {
+    let taken: HashSet<String> = lines.iter()
+        .flat_map(|line| line.text.split(|c: char| !is_word(c)))
+        .filter(|word| !word.is_empty() && word.chars().all(|c| c == 'S' || c == 'T'))
+        .map(String::from)
+        .collect();
+
+    let mut labels: HashMap<String, String> = HashMap::new();
+    let mut next = 1u64;
+
+    for line in lines {
+        line.text = substitute(&line.text, |word| {
+            if !word.starts_with('@') {
+                return None;
+            };
+
+            let label = labels.entry(word.to_string()).or_insert_with(|| loop {
+                let label: String = numbered_label(next).chars().map(|c| if c == ' ' {'S'} else {'T'}).collect();
+
+                next += 1;
+
+                if !taken.contains(&label) {
+                    break label;
+                };
+            });
+
+            Some(label.clone())
+        });
+    }
+}
+
+/// Resolves `path` as far as possible, so that the same file is recognised
+/// however it is reached.
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// The commands that print `string`, or store it from `address` on.
+fn string_commands(string: &str, address: Option<Value>) -> Vec<CommandType> {
+    let character = |c: char| CommandType::Push(Value::from(c as u32));
+
+    match address {
+        None => string.chars()
+            .flat_map(|c| [character(c), CommandType::OutC])
+            .collect(),
+
+        Some(address) => string.chars()
+            .chain(['\0'])
+            .enumerate()
+            .flat_map(|(offset, c)| [CommandType::Push(&address + offset), character(c), CommandType::Store])
+            .collect(),
+    }
+}
+
+/// Splits off the first word of `code`, returning it and the trimmed rest.
+fn split_word(code: &str) -> (&str, &str) {
+    match code.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim()),
+        None => (code, ""),
+    }
+}
+
+/// Splits a list of arguments on the commas that are not in quotes.
+fn split_arguments(text: &str) -> Vec<&str> {
+    if text.trim().is_empty() {
+        return vec![];
+    };
+
+    let mut arguments = vec![];
+    let mut quote = None;
+    let mut start = 0;
+
+    for (index, c) in text.char_indices() {
+        match (c, quote) {
+            ('\'' | '"', None) => quote = Some(c),
+            (_, Some(open)) if c == open => quote = None,
+
+            (',', None) => {
+                arguments.push(text[start..index].trim());
+
+                start = index + 1;
+            },
+
+            _ => {},
+        };
+    }
+
+    arguments.push(text[start..].trim());
+
+    arguments
+}
+
+/// Replaces each word of `code` that is outside of quotes with whatever
+/// `replace` gives for it, if anything.
+fn substitute(code: &str, mut replace: impl FnMut(&str) -> Option<String>) -> String {
+    let mut output = String::with_capacity(code.len());
+    let mut quote = None;
+    let mut word = String::new();
+
+    let mut flush = |word: &mut String, output: &mut String| {
+        match replace(word) {
+            Some(replacement) if !word.is_empty() => output.push_str(&replacement),
+            _ => output.push_str(word),
+        };
+
+        word.clear();
+    };
+
+    for c in code.chars() {
+        if quote.is_none() && is_word(c) {
+            word.push(c);
+
+            continue;
+        };
+
+        flush(&mut word, &mut output);
+
+        match (c, quote) {
+            ('\'' | '"', None) => quote = Some(c),
+            (_, Some(open)) if c == open => quote = None,
+
+            _ => {},
+        };
+
+        output.push(c);
+    }
+
+    flush(&mut word, &mut output);
+
+    output
+}
+
+/// Whether `c` can be part of a word that [`substitute`] replaces.
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '@' | '.' | '$')
+}
+
+/// Reads a string in double quotes, with `\n`, `\t`, `\0`, `\\` and `\"`
+/// escapes.
+fn parse_string(text: &str) -> Option<String> {
+    let inner = text.strip_prefix('"')?.strip_suffix('"')?;
+
+    let mut string = String::new();
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next() {
+        string.push(match c {
+            '\\' => match chars.next()? {
+                'n' => '\n',
+                't' => '\t',
+                '0' => '\0',
+                '\\' => '\\',
+                '"' => '"',
+
+                _ => return None,
+            },
+
+            '"' => return None,
+
+            c => c,
+        });
+    }
+
+    Some(string)
+}
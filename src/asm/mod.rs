@@ -0,0 +1,6 @@
+pub mod asm_error;
+pub mod assembler;
+pub mod builder;
+pub mod disassembler;
+pub mod macro_assembler;
+pub mod syntax;
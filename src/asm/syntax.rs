@@ -0,0 +1,194 @@
+use crate::vm::number::Value;
+use crate::vm::parser::token::CommandType;
+
+use std::mem;
+
+/// A convention for writing Whitespace as mnemonics.
+///
+/// Mnemonics are matched without regard to case. Every syntax also accepts a
+/// label written as its exact spaces and tabs, as `S` and `T` after a `%`,
+/// and an instruction outside the language written as `S`, `T` and `L` the
+/// same way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Syntax {
+    /// This crate's own mnemonics, such as `push`, `jz` and `outc`, with
+    /// named labels and decimal numbers.
+    #[default]
+    Native,
+    /// The `wsa` assembly used by most other Whitespace assemblers, such as
+    /// `doub`, `retrive`, `jumpz` and `outN`. Numbers may also be written as
+    /// a character in single quotes, which stands for its code point.
+    ///
+    /// Its shorthands are assembled into several instructions: `add`, `sub`,
+    /// `mul`, `div`, `mod`, `store` and `retrive` may be given a number to
+    /// push first, and `jumpp`, `jumpnp`, `jumpnz` and `jumppz` jump on a
+    /// positive, non-zero, non-positive and non-negative value, also with the
+    /// two letters after `jump` swapped. The debugging extensions are
+    /// `debug_printstack` and `debug_printheap`. Directives such as `include`
+    /// and `valuestring` are not supported.
+    Wsa,
+    /// The names of the instructions in the reference interpreter, such as
+    /// `ref`, `plus` and `ifzero`. Labels are written as their spaces
+    /// and tabs, as `S` and `T`, like the reference interpreter shows them.
+    Reference,
+}
+
+impl Syntax {
+    /// Looks a syntax up by the name the command line uses for it.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "native" => Some(Syntax::Native),
+            "wsa" => Some(Syntax::Wsa),
+            "reference" => Some(Syntax::Reference),
+
+            _ => None,
+        }
+    }
+
+    /// Every mnemonic of the syntax, paired with the command it stands for.
+    /// Arguments in the commands are placeholders. When a command has several
+    /// mnemonics, the first is the one it is written with.
+    fn mnemonics(&self) -> &'static [(&'static str, CommandType)] {
+        match self {
+            Syntax::Native => &NATIVE,
+            Syntax::Wsa => &WSA,
+            Syntax::Reference => &REFERENCE,
+        }
+    }
+
+    /// The command that `mnemonic` stands for, with placeholder arguments.
+    pub fn command(&self, mnemonic: &str) -> Option<&'static CommandType> {
+        self.mnemonics().iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(mnemonic))
+            .map(|(_, command)| command)
+    }
+
+    /// The mnemonic `command` is written with, ignoring its argument. `None`
+    /// for extension instructions, which are written by name.
+    pub fn mnemonic(&self, command: &CommandType) -> Option<&'static str> {
+        self.mnemonics().iter()
+            .find(|(_, template)| mem::discriminant(template) == mem::discriminant(command))
+            .map(|(name, _)| *name)
+    }
+
+    /// The name of the extension that `mnemonic` stands for. `wsa` spells the
+    /// debugging extensions without their last underscore.
+    pub fn extension_name<'a>(&self, mnemonic: &'a str) -> &'a str {
+        match (self, mnemonic) {
+            (Syntax::Wsa, "debug_printstack") => "debug_print_stack",
+            (Syntax::Wsa, "debug_printheap") => "debug_print_heap",
+
+            _ => mnemonic,
+        }
+    }
+
+    /// Whether labels are written as their spaces and tabs rather than named.
+    pub fn has_raw_labels(&self) -> bool {
+        *self == Syntax::Reference
+    }
+
+    /// Reads a numeric argument.
+    pub fn number(&self, text: &str) -> Option<Value> {
+        if *self == Syntax::Wsa {
+            let mut chars = text.chars();
+
+            if let (Some('\''), Some(c), Some('\''), None) = (chars.next(), chars.next(), chars.next(), chars.next()) {
+                return Some(Value::from(c as u32));
+            };
+        };
+
+        text.parse().ok()
+    }
+}
+
+static NATIVE: [(&str, CommandType); 25] = [
+    ("push", CommandType::Push(Value::ZERO)),
+    ("dup", CommandType::Dup),
+    ("copy", CommandType::Copy(Value::ZERO)),
+    ("swap", CommandType::Swap),
+    ("drop", CommandType::Disc),
+    ("disc", CommandType::Disc),
+    ("slide", CommandType::Slide(Value::ZERO)),
+
+    ("add", CommandType::Add),
+    ("sub", CommandType::Sub),
+    ("mul", CommandType::Mult),
+    ("div", CommandType::IDiv),
+    ("mod", CommandType::Mod),
+    ("store", CommandType::Store),
+    ("retr", CommandType::Retr),
+
+    ("label", CommandType::Labl(String::new())),
+    ("call", CommandType::Call(String::new())),
+    ("jump", CommandType::Jump(String::new())),
+    ("jz", CommandType::JumpZ(String::new())),
+    ("jn", CommandType::JumpN(String::new())),
+    ("ret", CommandType::EndS),
+    ("end", CommandType::EndP),
+
+    ("outc", CommandType::OutC),
+    ("outi", CommandType::OutI),
+    ("readc", CommandType::ReadC),
+    ("readi", CommandType::ReadI),
+];
+
+static WSA: [(&str, CommandType); 25] = [
+    ("push", CommandType::Push(Value::ZERO)),
+    ("doub", CommandType::Dup),
+    ("copy", CommandType::Copy(Value::ZERO)),
+    ("swap", CommandType::Swap),
+    ("pop", CommandType::Disc),
+    ("slide", CommandType::Slide(Value::ZERO)),
+
+    ("add", CommandType::Add),
+    ("sub", CommandType::Sub),
+    ("mul", CommandType::Mult),
+    ("div", CommandType::IDiv),
+    ("mod", CommandType::Mod),
+    ("store", CommandType::Store),
+    ("retrive", CommandType::Retr),
+    ("retrieve", CommandType::Retr),
+
+    ("label", CommandType::Labl(String::new())),
+    ("call", CommandType::Call(String::new())),
+    ("jump", CommandType::Jump(String::new())),
+    ("jumpz", CommandType::JumpZ(String::new())),
+    ("jumpn", CommandType::JumpN(String::new())),
+    ("ret", CommandType::EndS),
+    ("exit", CommandType::EndP),
+
+    ("outC", CommandType::OutC),
+    ("outN", CommandType::OutI),
+    ("inC", CommandType::ReadC),
+    ("inN", CommandType::ReadI),
+];
+
+static REFERENCE: [(&str, CommandType); 24] = [
+    ("push", CommandType::Push(Value::ZERO)),
+    ("dup", CommandType::Dup),
+    ("ref", CommandType::Copy(Value::ZERO)),
+    ("swap", CommandType::Swap),
+    ("discard", CommandType::Disc),
+    ("slide", CommandType::Slide(Value::ZERO)),
+
+    ("plus", CommandType::Add),
+    ("minus", CommandType::Sub),
+    ("times", CommandType::Mult),
+    ("divide", CommandType::IDiv),
+    ("modulo", CommandType::Mod),
+    ("store", CommandType::Store),
+    ("retrieve", CommandType::Retr),
+
+    ("label", CommandType::Labl(String::new())),
+    ("call", CommandType::Call(String::new())),
+    ("jump", CommandType::Jump(String::new())),
+    ("ifzero", CommandType::JumpZ(String::new())),
+    ("ifnegative", CommandType::JumpN(String::new())),
+    ("return", CommandType::EndS),
+    ("end", CommandType::EndP),
+
+    ("outputchar", CommandType::OutC),
+    ("outputnum", CommandType::OutI),
+    ("readchar", CommandType::ReadC),
+    ("readnum", CommandType::ReadI),
+];
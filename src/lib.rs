@@ -0,0 +1,58 @@
+//! An implementation of the Whitespace programming language.
+//!
+//! A program is run in two phases: the [`Parser`] turns source into a
+//! [`Program`], and the [`VirtualMachine`] executes its instructions.
+//!
+//! ```no_run
+//! use whitespace::{Parser, VirtualMachine};
+//!
+//! let program = Parser::new(String::from("   \t\n\t\n \t\n\n\n"))
+//!     .parse()
+//!     .expect("could not parse program");
+//!
+//! let mut virtual_machine = VirtualMachine::new(program);
+//! virtual_machine.run().expect("could not run program");
+//! ```
+//!
+//! [`VirtualMachine::with_io`] runs a program against any reader and writer
+//! instead of the process's stdin and stdout:
+//!
+//! ```
+//! use whitespace::{Parser, VirtualMachine};
+//!
+//! let program = Parser::new(String::from("   \t\n\t\n \t\n\n\n")).parse().unwrap();
+//!
+//! let mut virtual_machine = VirtualMachine::with_io(program, &b""[..], Vec::new());
+//! virtual_machine.run().unwrap();
+//!
+//! assert_eq!(virtual_machine.output(), b"1");
+//! ```
+
+mod asm;
+mod vm;
+
+pub use num_bigint;
+
+pub use asm::asm_error::{AsmError, AsmErrorKind, Origin};
+pub use asm::assembler::{assemble, Assembler};
+pub use asm::builder::ProgramBuilder;
+pub use asm::disassembler::{disassemble, Disassembler};
+pub use asm::macro_assembler::MacroAssembler;
+pub use asm::syntax::Syntax;
+
+pub use vm::parser::alphabet::{Alphabet, WhitespacePolicy};
+pub use vm::parser::encoder::{encode, encode_instruction, encode_program};
+pub use vm::parser::notation::Notation;
+pub use vm::parser::parser::{Lexemes, ParseOutput, Parser};
+pub use vm::parser::parser_error::{ParserError, ParserErrorKind};
+pub use vm::parser::syntax_tree::{SyntaxNode, SyntaxTree};
+pub use vm::parser::token::{CommandType, ImpType, Location, Opcode, Token, TokenType};
+
+pub use vm::dialect::Dialect;
+pub use vm::extensions::{Extension, ExtensionContext, Extensions};
+pub use vm::limits::{Limit, Limits};
+pub use vm::number::{NumericMode, Value};
+pub use vm::program::{Instruction, Program};
+
+pub use vm::virtual_machine::{Status, VirtualMachine};
+pub use vm::vm_error::{VMError, VMErrorKind};
@@ -1,32 +1,208 @@
-mod vm;
+use std::fs;
 
-use std::{fs, io::Read};
+use whitespace::{CommandType, Dialect, Disassembler, Extensions, MacroAssembler, Notation, ParseOutput, Parser, Program, Syntax, TokenType, VirtualMachine};
 
-fn main() {
-    let arg = std::env::args().nth(1).expect("No source passed into program");
+const USAGE: &str = "\
+Usage: whitespace [run] [--notation <notation>] [--dialect <dialect>] [--debug-extensions] <file or source>
+       whitespace convert [--notation <notation>] [--dialect <dialect>] --to <notation> <file or source>
+       whitespace asm [--syntax <syntax>] [--to <notation>] [--debug-extensions] <file or source>
+       whitespace disasm [--syntax <syntax>] [--notation <notation>] [--dialect <dialect>] [--debug-extensions] <file or source>
+
+Notations are whitespace (the default), stl and bracketed.
+Dialects are permissive (the default), 0.2 and 0.3.
+Syntaxes are native (the default), wsa and reference.";
+
+/// The command line, after the subcommand.
+struct Options {
+    source: String,
+    notation: Notation,
+    to: Option<Notation>,
+    syntax: Syntax,
+    dialect: Dialect,
+    extensions: Extensions,
+}
 
-    let mut source: String = String::new();
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Self {
+        let mut options = Self {
+            source: String::new(),
+            notation: Notation::default(),
+            to: None,
+            syntax: Syntax::default(),
+            dialect: Dialect::default(),
+            extensions: Extensions::new(),
+        };
 
-    match fs::File::open(arg.clone()) {
-        Ok(mut file) => {
-            if let Err(_) = file.read_to_string(&mut source) {
-                panic!("Could not read from file");
+        let mut source = None;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--notation" => options.notation = notation(args.next()),
+                "--to" => options.to = Some(notation(args.next())),
+                "--syntax" => options.syntax = syntax(args.next()),
+                "--dialect" => options.dialect = dialect(args.next()),
+                "--debug-extensions" => options.extensions = Extensions::debug(),
+
+                _ if source.is_none() => source = Some(arg),
+                _ => usage(),
             }
-        },
+        }
+
+        options.source = source.unwrap_or_else(|| usage());
+
+        options
+    }
+
+    /// Reads the source from the named file, or takes the argument itself as
+    /// the source if there is no such file.
+    fn parse_source(&self) -> ParseOutput {
+        let output = match fs::File::open(&self.source) {
+            Ok(file) => Parser::from_reader(file)
+                .with_notation(self.notation)
+                .with_dialect(self.dialect)
+                .with_extensions(&self.extensions)
+                .parse_all(),
+
+            _ => Parser::new(self.source.clone())
+                .with_notation(self.notation)
+                .with_dialect(self.dialect)
+                .with_extensions(&self.extensions)
+                .parse_all(),
+        };
+
+        if !output.errors.is_empty() {
+            for parser_error in &output.errors {
+                eprintln!("{}", parser_error);
+            }
+
+            std::process::exit(1);
+        };
+
+        output
+    }
+
+    /// Parses the source and links it into a program.
+    fn parse_program(&self) -> Program {
+        match Program::link(self.parse_source().tokens) {
+            Ok(program) => program,
+            Err(parser_error) => {
+                eprintln!("{}", parser_error);
+
+                std::process::exit(1);
+            },
+        }
+    }
+}
+
+fn notation(name: Option<String>) -> Notation {
+    match name.as_deref().and_then(Notation::from_name) {
+        Some(notation) => notation,
+        None => usage(),
+    }
+}
+
+fn syntax(name: Option<String>) -> Syntax {
+    match name.as_deref().and_then(Syntax::from_name) {
+        Some(syntax) => syntax,
+        None => usage(),
+    }
+}
+
+fn dialect(name: Option<String>) -> Dialect {
+    match name.as_deref().and_then(Dialect::from_name) {
+        Some(dialect) => dialect,
+        None => usage(),
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+
+    std::process::exit(2);
+}
+
+fn run(options: Options) {
+    let program = options.parse_program();
 
-        _ => {
-            source = arg;
+    let mut virtual_machine = VirtualMachine::new(program)
+        .with_dialect(options.dialect)
+        .with_extensions(options.extensions);
+
+    if let Err(vm_error) = virtual_machine.run() {
+        eprintln!("{}", vm_error);
+
+        std::process::exit(1);
+    };
+}
+
+fn convert(options: Options) {
+    let Some(to) = options.to else {
+        usage()
+    };
+
+    let output = options.parse_source();
+
+    let commands = output.tokens.iter().filter_map(|token| match &token.token_type {
+        TokenType::Command(command) => Some(command),
+        _ => None,
+    });
+
+    print_commands(to, commands);
+}
+
+fn asm(options: Options) {
+    let assembler = MacroAssembler::new()
+        .with_syntax(options.syntax)
+        .with_extensions(&options.extensions);
+
+    let assembled = match fs::read_to_string(&options.source) {
+        Ok(source) => assembler.assemble_file(&source, &options.source),
+        _ => assembler.assemble(&options.source),
+    };
+
+    let commands = match assembled {
+        Ok(commands) => commands,
+        Err(asm_error) => {
+            eprintln!("{}", asm_error);
+
+            std::process::exit(1);
         },
     };
-    
-    let mut parser = vm::parser::parser::Parser::new(source);
-    let mut virtual_machine = vm::virtual_machine::VirtualMachine::new(&mut parser);
 
-    if let Err(parser_error) = virtual_machine.parse() {
-        panic!("{}", parser_error);
+    print_commands(options.to.unwrap_or_default(), &commands);
+}
+
+fn disasm(options: Options) {
+    let program = options.parse_program();
+
+    let disassembler = Disassembler::new()
+        .with_syntax(options.syntax)
+        .with_extensions(&options.extensions);
+
+    print!("{}", disassembler.disassemble(&program));
+}
+
+fn print_commands<'a>(notation: Notation, commands: impl IntoIterator<Item = &'a CommandType>) {
+    let rendered = notation.render_commands(commands);
+
+    if notation == Notation::Whitespace {
+        print!("{}", rendered);
+    } else {
+        println!("{}", rendered);
     };
+}
 
-    if let Err(vm_error) = virtual_machine.run() {
-        panic!("{}", vm_error);
+fn main() {
+    let mut args = std::env::args().skip(1).peekable();
+
+    match args.peek().map(String::as_str) {
+        Some("run") => run(Options::parse(args.skip(1))),
+        Some("convert") => convert(Options::parse(args.skip(1))),
+        Some("asm") => asm(Options::parse(args.skip(1))),
+        Some("disasm") => disasm(Options::parse(args.skip(1))),
+        Some("-h" | "--help") | None => usage(),
+
+        // Running a program used to be the only thing the binary did
+        Some(_) => run(Options::parse(args)),
     };
 }
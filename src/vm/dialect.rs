@@ -0,0 +1,42 @@
+/// Which version of Whitespace a program is written in, and how closely the
+/// reference interpreter's behaviour is followed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Dialect {
+    /// Whitespace 0.3 with this interpreter's own lenient behaviour: reads
+    /// leave their heap address on the stack, division truncates, returning
+    /// without a call moves on to the next instruction and a program may end
+    /// without `EndP`.
+    #[default]
+    Permissive,
+    /// Whitespace 0.2, which has no `Copy` or `Slide`, run like the reference
+    /// interpreter.
+    Whitespace02,
+    /// Whitespace 0.3 run like the reference interpreter.
+    Whitespace03,
+}
+
+impl Dialect {
+    /// Looks a dialect up by the name the command line uses for it.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "permissive" => Some(Dialect::Permissive),
+            "0.2" => Some(Dialect::Whitespace02),
+            "0.3" => Some(Dialect::Whitespace03),
+
+            _ => None,
+        }
+    }
+
+    /// Whether the `Copy` and `Slide` stack commands exist.
+    pub fn has_copy_and_slide(&self) -> bool {
+        *self != Dialect::Whitespace02
+    }
+
+    /// Whether programs run with the semantics of the reference interpreter:
+    /// reads pop their heap address, division and modulo round towards
+    /// negative infinity, and both returning without a call and running past
+    /// the last instruction are errors.
+    pub fn is_strict(&self) -> bool {
+        *self != Dialect::Permissive
+    }
+}
\ No newline at end of file
@@ -0,0 +1,229 @@
+use super::limits::Limits;
+use super::number::{NumericMode, Value};
+use super::parser::token::{ImpType, Opcode};
+use super::vm_error::VMErrorKind;
+
+use num_traits::Signed;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+use std::sync::Arc;
+
+/// What an extension can see and change while it runs.
+///
+/// The stack and heap can only be changed through the same checks that
+/// instructions go through, so the machine's [`Limits`] and [`NumericMode`]
+/// still hold. When one of them fails, returning its message as the error
+/// stops the program with the machine's own [`VMErrorKind`].
+pub struct ExtensionContext<'a> {
+    stack: &'a mut Vec<Value>,
+    heap: &'a mut HashMap<Value, Value>,
+
+    limits: Limits,
+    numeric_mode: NumericMode,
+    error: Option<VMErrorKind>,
+
+    pub output: &'a mut dyn Write,
+}
+
+impl<'a> ExtensionContext<'a> {
+    pub(crate) fn new(stack: &'a mut Vec<Value>, heap: &'a mut HashMap<Value, Value>, limits: Limits, numeric_mode: NumericMode, output: &'a mut dyn Write) -> Self {
+        Self {
+            stack,
+            heap,
+
+            limits,
+            numeric_mode,
+            error: None,
+
+            output,
+        }
+    }
+
+    /// The stack, with its top last.
+    pub fn stack(&self) -> &[Value] {
+        self.stack
+    }
+
+    pub fn heap(&self) -> &HashMap<Value, Value> {
+        self.heap
+    }
+
+    pub fn push(&mut self, n: Value) -> Result<(), String> {
+        let n = self.normalize(n)?;
+
+        self.check(self.limits.check_stack(self.stack.len()))?;
+
+        self.stack.push(n);
+
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Result<Value, String> {
+        match self.stack.pop() {
+            Some(n) => Ok(n),
+            None => self.check(Err(VMErrorKind::StackUnderflow)),
+        }
+    }
+
+    pub fn store(&mut self, address: Value, n: Value) -> Result<(), String> {
+        if address.is_negative() {
+            return self.check(Err(VMErrorKind::NegativeHeapAddress(address)));
+        };
+
+        let n = self.normalize(n)?;
+
+        self.check(self.limits.check_heap(self.heap, &address))?;
+
+        self.heap.insert(address, n);
+
+        Ok(())
+    }
+
+    /// The value at `address`, which is 0 for cells that were never written.
+    pub fn retrieve(&self, address: &Value) -> Value {
+        self.heap.get(address).cloned().unwrap_or_default()
+    }
+
+    /// The last check that failed, if any.
+    pub(crate) fn into_error(self) -> Option<VMErrorKind> {
+        self.error
+    }
+
+    fn normalize(&mut self, n: Value) -> Result<Value, String> {
+        let numeric_mode = self.numeric_mode;
+
+        self.check(numeric_mode.normalize(n).map_err(|value| VMErrorKind::Overflow { value, mode: numeric_mode }))
+    }
+
+    /// Remembers a failed check, and turns it into a message for the callback.
+    fn check<T>(&mut self, result: Result<T, VMErrorKind>) -> Result<T, String> {
+        result.map_err(|kind| {
+            let message = kind.to_string();
+
+            self.error = Some(kind);
+
+            message
+        })
+    }
+}
+
+/// Runs an extension instruction. An error message stops the program.
+pub type Callback = dyn Fn(&mut ExtensionContext) -> Result<(), String> + Send + Sync;
+
+#[derive(Clone)]
+pub struct Extension {
+    pub name: String,
+    pub opcode: Opcode,
+
+    callback: Arc<Callback>,
+}
+
+impl Extension {
+    pub fn call(&self, context: &mut ExtensionContext) -> Result<(), String> {
+        (self.callback)(context)
+    }
+}
+
+/// The command patterns of the standard language, for every IMP.
+const COMMANDS: [(ImpType, &[&str]); 5] = [
+    (ImpType::Stack, &[" ", "\n ", "\n\t", "\n\n", "\t ", "\t\n"]),
+    (ImpType::Arithmetic, &["  ", " \t", " \n", "\t ", "\t\t"]),
+    (ImpType::Heap, &[" ", "\t"]),
+    (ImpType::Flow, &["  ", " \t", " \n", "\t ", "\t\t", "\t\n", "\n\n"]),
+    (ImpType::IO, &["  ", " \t", "\t ", "\t\t"]),
+];
+
+/// A registry of extension instructions, each bound to a command pattern that
+/// the language leaves unused.
+///
+/// The same registry has to be given to the [`Parser`](super::parser::parser::Parser)
+/// that reads a program and to the [`VirtualMachine`](super::virtual_machine::VirtualMachine)
+/// that runs it.
+#[derive(Clone, Default)]
+pub struct Extensions {
+    extensions: Vec<Extension>,
+}
+
+impl Extensions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry with the built-in debugging extensions, which print the
+    /// stack (IO `LS`) or the heap (IO `LT`) to the program's output.
+    pub fn debug() -> Self {
+        let mut extensions = Self::new();
+
+        extensions.register("debug_print_stack", Opcode::new(ImpType::IO, "\n "), |context| {
+            let stack: Vec<String> = context.stack().iter().map(|n| n.to_string()).collect();
+
+            writeln!(context.output, "[{}]", stack.join(", ")).map_err(|e| e.to_string())
+        });
+
+        extensions.register("debug_print_heap", Opcode::new(ImpType::IO, "\n\t"), |context| {
+            let mut heap: Vec<(&Value, &Value)> = context.heap().iter().collect();
+
+            heap.sort();
+
+            let heap: Vec<String> = heap.iter().map(|(index, n)| format!("{}: {}", index, n)).collect();
+
+            writeln!(context.output, "{{{}}}", heap.join(", ")).map_err(|e| e.to_string())
+        });
+
+        extensions
+    }
+
+    /// Binds `callback` to `opcode`.
+    ///
+    /// # Panics
+    ///
+    /// If the pattern is empty, contains anything other than spaces, tabs and
+    /// linefeeds, or overlaps a standard command or another extension, since
+    /// the parser could then not tell them apart.
+    pub fn register(&mut self, name: &str, opcode: Opcode, callback: impl Fn(&mut ExtensionContext) -> Result<(), String> + Send + Sync + 'static) {
+        assert!(!opcode.pattern.is_empty() && opcode.pattern.chars().all(|c| c == ' ' || c == '\t' || c == '\n'), "extension {} has an invalid pattern", name);
+        assert!(!overlaps_standard(&opcode), "extension {} overlaps a standard command", name);
+
+        for extension in &self.extensions {
+            assert!(extension.opcode.imp != opcode.imp || !overlaps(&extension.opcode.pattern, &opcode.pattern), "extension {} overlaps extension {}", name, extension.name);
+        }
+
+        self.extensions.push(Extension {
+            name: name.to_string(),
+            opcode,
+
+            callback: Arc::new(callback),
+        });
+    }
+
+    pub fn get(&self, opcode: &Opcode) -> Option<&Extension> {
+        self.extensions.iter().find(|extension| extension.opcode == *opcode)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Extension> {
+        self.extensions.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.extensions.is_empty()
+    }
+}
+
+/// Whether one of the patterns starts with the other, so the parser could not
+/// tell them apart.
+fn overlaps(a: &str, b: &str) -> bool {
+    a.starts_with(b) || b.starts_with(a)
+}
+
+/// Whether `opcode` overlaps a command of the standard language.
+pub(crate) fn overlaps_standard(opcode: &Opcode) -> bool {
+    COMMANDS.iter().any(|(imp, patterns)| *imp == opcode.imp && patterns.iter().any(|pattern| overlaps(pattern, &opcode.pattern)))
+}
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.extensions.iter().map(|extension| (&extension.name, &extension.opcode))).finish()
+    }
+}
\ No newline at end of file
@@ -0,0 +1,52 @@
+use super::number::Value;
+use super::vm_error::VMErrorKind;
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// Caps on how much memory a single [`VirtualMachine`](super::virtual_machine::VirtualMachine)
+/// may use. `None` means unbounded, which is the default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// The most values the stack may hold at once.
+    pub max_stack: Option<usize>,
+    /// The most distinct heap cells that may be written.
+    pub max_heap: Option<usize>,
+    /// The most subroutine calls that may be active at once.
+    pub max_call_depth: Option<usize>,
+}
+
+impl Limits {
+    /// Fails if a stack of `len` values may not grow any further.
+    pub(crate) fn check_stack(&self, len: usize) -> Result<(), VMErrorKind> {
+        match self.max_stack {
+            Some(max) if len >= max => Err(VMErrorKind::LimitExceeded { limit: Limit::Stack, max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails if writing to `index` would take a new cell beyond the heap limit.
+    pub(crate) fn check_heap(&self, heap: &HashMap<Value, Value>, index: &Value) -> Result<(), VMErrorKind> {
+        match self.max_heap {
+            Some(max) if heap.len() >= max && !heap.contains_key(index) => Err(VMErrorKind::LimitExceeded { limit: Limit::Heap, max }),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Stack,
+    Heap,
+    CallDepth,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Limit::Stack => write!(f, "stack size"),
+            Limit::Heap => write!(f, "heap size"),
+            Limit::CallDepth => write!(f, "call depth"),
+        }
+    }
+}
\ No newline at end of file
@@ -1,4 +1,10 @@
 pub mod vm_error;
 
+pub mod dialect;
+pub mod extensions;
+pub mod limits;
+pub mod number;
+
 pub mod parser;
+pub mod program;
 pub mod virtual_machine;
\ No newline at end of file
@@ -0,0 +1,63 @@
+use num_bigint::BigInt;
+use num_traits::{One, ToPrimitive};
+
+use std::fmt;
+
+/// The type of every number on the stack, in the heap and in the source.
+///
+/// Values are stored with arbitrary precision; the [`NumericMode`] decides
+/// which of them a program is allowed to produce.
+pub type Value = BigInt;
+
+/// How numbers behave when they leave the range of a fixed width integer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NumericMode {
+    /// 32-bit integers where overflow is an error.
+    #[default]
+    Checked,
+    /// 32-bit integers that wrap around on overflow.
+    Wrapping,
+    /// 64-bit integers where overflow is an error.
+    I64,
+    /// Integers without any bound.
+    BigInt,
+}
+
+impl NumericMode {
+    /// Whether `n` is a valid literal or input in this mode.
+    pub fn contains(&self, n: &Value) -> bool {
+        match self {
+            NumericMode::Checked | NumericMode::Wrapping => n.to_i32().is_some(),
+            NumericMode::I64 => n.to_i64().is_some(),
+            NumericMode::BigInt => true,
+        }
+    }
+
+    /// Brings the result of an arithmetic operation back into range, or hands
+    /// it back as an error if it overflowed.
+    pub fn normalize(&self, n: Value) -> Result<Value, Value> {
+        match self {
+            NumericMode::Wrapping => {
+                let modulus = Value::one() << 32;
+                let half = Value::one() << 31;
+
+                let wrapped = ((n + &half) % &modulus + &modulus) % &modulus - half;
+
+                Ok(wrapped)
+            },
+
+            _ => if self.contains(&n) {Ok(n)} else {Err(n)},
+        }
+    }
+}
+
+impl fmt::Display for NumericMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NumericMode::Checked => write!(f, "checked 32-bit"),
+            NumericMode::Wrapping => write!(f, "wrapping 32-bit"),
+            NumericMode::I64 => write!(f, "64-bit"),
+            NumericMode::BigInt => write!(f, "arbitrary precision"),
+        }
+    }
+}
\ No newline at end of file
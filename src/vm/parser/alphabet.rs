@@ -0,0 +1,101 @@
+/// What happens to whitespace characters that are not part of an
+/// [`Alphabet`], such as no-break spaces, vertical tabs, form feeds or
+/// carriage returns.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WhitespacePolicy {
+    /// They are comments, like any other character outside the alphabet.
+    #[default]
+    Comment,
+    /// They are skipped and do not count towards the columns of later
+    /// characters, so invisible characters do not shift reported positions.
+    /// Linefeeds still start a new line.
+    Ignore,
+    /// They are reported as errors.
+    Error,
+}
+
+/// The three characters that a source uses in place of space, tab and
+/// linefeed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alphabet {
+    space: char,
+    tab: char,
+    linefeed: char,
+
+    other_whitespace: WhitespacePolicy,
+}
+
+impl Alphabet {
+    /// Panics if the characters are not all different.
+    pub fn new(space: char, tab: char, linefeed: char) -> Self {
+        assert!(space != tab && space != linefeed && tab != linefeed, "alphabet characters must be distinct");
+
+        Self {
+            space,
+            tab,
+            linefeed,
+
+            other_whitespace: WhitespacePolicy::default(),
+        }
+    }
+
+    /// Space, tab and linefeed, as in standard Whitespace.
+    pub fn standard() -> Self {
+        Self::new(' ', '\t', '\n')
+    }
+
+    /// Zero-width space, zero-width non-joiner and zero-width joiner, which
+    /// hide a program completely inside ordinary text.
+    pub fn zero_width() -> Self {
+        Self::new('\u{200B}', '\u{200C}', '\u{200D}')
+    }
+
+    /// Sets what happens to whitespace outside the alphabet.
+    pub fn with_other_whitespace(mut self, policy: WhitespacePolicy) -> Self {
+        self.other_whitespace = policy;
+
+        self
+    }
+
+    pub fn space(&self) -> char {
+        self.space
+    }
+
+    pub fn tab(&self) -> char {
+        self.tab
+    }
+
+    pub fn linefeed(&self) -> char {
+        self.linefeed
+    }
+
+    pub fn other_whitespace(&self) -> WhitespacePolicy {
+        self.other_whitespace
+    }
+
+    /// The space, tab or linefeed that `c` stands for, if any.
+    pub fn symbol(&self, c: char) -> Option<char> {
+        if c == self.space {
+            Some(' ')
+        } else if c == self.tab {
+            Some('\t')
+        } else if c == self.linefeed {
+            Some('\n')
+        } else {
+            None
+        }
+    }
+
+    /// Whether `c` is whitespace that is not in the alphabet. Zero-width
+    /// characters count as whitespace here even though Unicode does not
+    /// consider them to be.
+    pub fn is_other_whitespace(&self, c: char) -> bool {
+        self.symbol(c).is_none() && (c.is_whitespace() || matches!(c, '\u{200B}'..='\u{200D}' | '\u{2060}' | '\u{FEFF}'))
+    }
+}
+
+impl Default for Alphabet {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
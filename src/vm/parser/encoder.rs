@@ -0,0 +1,81 @@
+use super::token::{CommandType, ImpType};
+use super::super::number::Value;
+use super::super::program::Program;
+
+use num_traits::{Signed, Zero};
+
+/// Writes a command back out as the spaces, tabs and linefeeds the parser
+/// reads it from, starting with its IMP.
+pub fn encode(command: &CommandType) -> String {
+    let (imp, rest) = match command {
+        CommandType::Push(n) => (ImpType::Stack, format!(" {}", encode_number(n))),
+        CommandType::Dup => (ImpType::Stack, String::from("\n ")),
+        CommandType::Copy(n) => (ImpType::Stack, format!("\t {}", encode_number(n))),
+        CommandType::Swap => (ImpType::Stack, String::from("\n\t")),
+        CommandType::Disc => (ImpType::Stack, String::from("\n\n")),
+        CommandType::Slide(n) => (ImpType::Stack, format!("\t\n{}", encode_number(n))),
+
+        CommandType::Add => (ImpType::Arithmetic, String::from("  ")),
+        CommandType::Sub => (ImpType::Arithmetic, String::from(" \t")),
+        CommandType::Mult => (ImpType::Arithmetic, String::from(" \n")),
+        CommandType::IDiv => (ImpType::Arithmetic, String::from("\t ")),
+        CommandType::Mod => (ImpType::Arithmetic, String::from("\t\t")),
+        CommandType::Store => (ImpType::Heap, String::from(" ")),
+        CommandType::Retr => (ImpType::Heap, String::from("\t")),
+
+        CommandType::Labl(label) => (ImpType::Flow, format!("  {}", encode_label(label))),
+        CommandType::Call(label) => (ImpType::Flow, format!(" \t{}", encode_label(label))),
+        CommandType::Jump(label) => (ImpType::Flow, format!(" \n{}", encode_label(label))),
+        CommandType::JumpZ(label) => (ImpType::Flow, format!("\t {}", encode_label(label))),
+        CommandType::JumpN(label) => (ImpType::Flow, format!("\t\t{}", encode_label(label))),
+        CommandType::EndS => (ImpType::Flow, String::from("\t\n")),
+        CommandType::EndP => (ImpType::Flow, String::from("\n\n")),
+
+        CommandType::OutC => (ImpType::IO, String::from("  ")),
+        CommandType::OutI => (ImpType::IO, String::from(" \t")),
+        CommandType::ReadC => (ImpType::IO, String::from("\t ")),
+        CommandType::ReadI => (ImpType::IO, String::from("\t\t")),
+
+        CommandType::Extension(opcode) => (opcode.imp, opcode.pattern.clone()),
+    };
+
+    format!("{}{}", encode_imp(imp), rest)
+}
+
+/// Writes the instruction at `index` back out, with jumps naming the label
+/// they go to. `None` if there is no such instruction.
+pub fn encode_instruction(program: &Program, index: usize) -> Option<String> {
+    program.command(index).map(|command| encode(&command))
+}
+
+/// Writes a whole program back out, one instruction after the other.
+pub fn encode_program(program: &Program) -> String {
+    program.commands().map(|command| encode(&command)).collect()
+}
+
+pub fn encode_imp(imp: ImpType) -> &'static str {
+    match imp {
+        ImpType::Stack => " ",
+        ImpType::Arithmetic => "\t ",
+        ImpType::Heap => "\t\t",
+        ImpType::Flow => "\n",
+        ImpType::IO => "\t\n",
+    }
+}
+
+/// A sign, the magnitude in binary without leading zeros, and a linefeed.
+/// Zero has no digits at all.
+pub fn encode_number(n: &Value) -> String {
+    let sign = if n.is_negative() {'\t'} else {' '};
+
+    let digits = if n.is_zero() {String::new()} else {n.magnitude().to_str_radix(2)};
+
+    let digits: String = digits.chars().map(|c| if c == '0' {' '} else {'\t'}).collect();
+
+    format!("{}{}\n", sign, digits)
+}
+
+/// Labels are already spaces and tabs, so they only need their linefeed.
+pub fn encode_label(label: &str) -> String {
+    format!("{}\n", label)
+}
@@ -1,3 +1,8 @@
+pub mod alphabet;
+pub mod encoder;
+pub mod notation;
 pub mod parser_error;
 pub mod token;
+pub mod syntax_tree;
+#[allow(clippy::module_inception)]
 pub mod parser;
\ No newline at end of file
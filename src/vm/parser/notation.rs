@@ -0,0 +1,78 @@
+use super::encoder;
+use super::token::CommandType;
+
+/// A way of writing the three characters of a program down.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Notation {
+    /// Real spaces, tabs and linefeeds.
+    #[default]
+    Whitespace,
+    /// `S`, `T` and `L`, as in most tutorials.
+    Stl,
+    /// `[Space]`, `[Tab]` and `[LF]`, as in the language specification.
+    Bracketed,
+}
+
+impl Notation {
+    /// Looks a notation up by the name the command line uses for it.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "whitespace" | "ws" => Some(Notation::Whitespace),
+            "stl" => Some(Notation::Stl),
+            "bracketed" => Some(Notation::Bracketed),
+
+            _ => None,
+        }
+    }
+
+    /// How `c`, which must be a space, tab or linefeed, is written.
+    pub fn symbol(&self, c: char) -> &'static str {
+        match (self, c) {
+            (Notation::Whitespace, ' ') => " ",
+            (Notation::Whitespace, '\t') => "\t",
+            (Notation::Whitespace, '\n') => "\n",
+
+            (Notation::Stl, ' ') => "S",
+            (Notation::Stl, '\t') => "T",
+            (Notation::Stl, '\n') => "L",
+
+            (Notation::Bracketed, ' ') => "[Space]",
+            (Notation::Bracketed, '\t') => "[Tab]",
+            (Notation::Bracketed, '\n') => "[LF]",
+
+            _ => panic!("{:?} is not a space, tab or linefeed", c),
+        }
+    }
+
+    /// The space, tab or linefeed written as `word` in bracketed notation,
+    /// brackets included.
+    pub fn bracketed_symbol(word: &str) -> Option<char> {
+        match word {
+            "[Space]" => Some(' '),
+            "[Tab]" => Some('\t'),
+            "[LF]" => Some('\n'),
+
+            _ => None,
+        }
+    }
+
+    /// Rewrites the spaces, tabs and linefeeds of `whitespace` in this
+    /// notation, dropping every other character.
+    pub fn render(&self, whitespace: &str) -> String {
+        whitespace.chars()
+            .filter(|c| matches!(c, ' ' | '\t' | '\n'))
+            .map(|c| self.symbol(c))
+            .collect()
+    }
+
+    /// Writes out a whole program. The visible notations put each instruction
+    /// on its own line, which the parser reads as a comment.
+    pub fn render_commands<'a>(&self, commands: impl IntoIterator<Item = &'a CommandType>) -> String {
+        let separator = if *self == Notation::Whitespace {""} else {"\n"};
+
+        commands.into_iter()
+            .map(|command| self.render(&encoder::encode(command)))
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+}
@@ -1,5 +1,14 @@
+use super::alphabet::{Alphabet, WhitespacePolicy};
+use super::notation::Notation;
 use super::token::{Token, TokenType, ImpType, CommandType, Location};
-use super::parser_error::ParserError;
+use super::parser_error::{ParserError, ParserErrorKind};
+use super::super::program::Program;
+use super::super::dialect::Dialect;
+use super::super::extensions::Extensions;
+use super::super::number::{NumericMode, Value};
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Read};
 
 macro_rules! make_token {
     ($self: expr, $token_type: expr, $start:expr, $end: expr) => {
@@ -7,12 +16,7 @@ macro_rules! make_token {
             let tok = Ok(Token {
                 token_type: $token_type,
         
-                location: Location {
-                    start: $start,
-                    end: $end,
-    
-                    line: $self.line,
-                }
+                location: $self.span($end - $start),
             });
     
             for _ in $start..$end {
@@ -24,10 +28,14 @@ macro_rules! make_token {
     }
 }
 
+/// Fails on the `$length` characters starting at the pointer, reporting the
+/// last of them as unexpected unless the source ran out first.
 macro_rules! make_parser_error {
-    ($self: expr, $message: expr, $line: expr) => {
+    ($self: expr, $kind: expr, $length: expr, $expected: expr) => {
         {
-            let res = Err(ParserError::new(&format!($message, $line)));
+            let kind = if $self.peek_char($length - 1).is_none() {ParserErrorKind::UnexpectedEof} else {$kind};
+
+            let res = Err(ParserError::new(kind, $self.span($length), &$expected));
 
             $self.consume_char();
 
@@ -36,47 +44,299 @@ macro_rules! make_parser_error {
     }
 }
 
-pub struct Parser {
-    queue: std::collections::VecDeque<char>,
+/// Everything found by [`Parser::parse_all`]: the tokens that could be read
+/// and a diagnostic for each problem, in source order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOutput {
+    pub tokens: Vec<Token>,
+    pub errors: Vec<ParserError>,
+}
 
-    pointer: usize,
+/// A space, tab or linefeed together with where the character standing for
+/// it is in the original source.
+#[derive(Debug, Clone, Copy)]
+struct Symbol {
+    c: char,
+
+    offset: usize,
+    end: usize,
     line: usize,
+    column: usize,
+}
+
+/// Reads a program from any [`Read`] source.
+///
+/// The source is read in chunks as tokens are requested, and only a couple of
+/// characters of lookahead are kept, so a parser uses constant memory no
+/// matter how large the source is.
+pub struct Parser<R = io::Cursor<Vec<u8>>> {
+    reader: io::BufReader<R>,
+    io_error: Option<io::Error>,
+    at_eof: bool,
+    /// The bytes read so far of a character that spans several of them.
+    partial: Vec<u8>,
+    /// Whitespace outside the alphabet that has been read but not reported.
+    whitespace_errors: VecDeque<ParserError>,
+    /// Where an unfinished bracketed symbol starts, and what it says so far.
+    bracket: Option<(Symbol, String)>,
+
+    queue: VecDeque<Symbol>,
+    /// An IMP returned by `read_lexeme` whose command has not been read yet.
+    pending_imp: Option<Token>,
+    /// A token that was read but held back to report whitespace found in it.
+    pending_token: Option<Token>,
+
+    /// The position of the next byte to be read from `reader`.
+    offset: usize,
+    line: usize,
+    column: usize,
+
+    pointer: usize,
+    /// The byte offset just past the last consumed symbol.
+    consumed_end: usize,
+
+    numeric_mode: NumericMode,
+    dialect: Dialect,
+    extensions: Extensions,
+    alphabet: Alphabet,
+    notation: Notation,
 }
 
 impl Parser {
     pub fn new(source: String) -> Self {
-        let mut obj = Self {
-            queue: source.chars().collect(),
+        Self::from_reader(io::Cursor::new(source.into_bytes()))
+    }
+}
 
-            pointer: 0,
+impl<R: Read> Parser<R> {
+    /// Everything other than spaces, tabs and linefeeds is a comment, but still
+    /// counts towards the offsets, lines and columns of the tokens. The source
+    /// is decoded as UTF-8, and bytes that are not valid UTF-8 are comments.
+    pub fn from_reader(reader: R) -> Self {
+        Self {
+            reader: io::BufReader::new(reader),
+            io_error: None,
+            at_eof: false,
+            partial: vec![],
+            whitespace_errors: VecDeque::new(),
+            bracket: None,
+
+            queue: VecDeque::new(),
+            pending_imp: None,
+            pending_token: None,
+
+            offset: 0,
             line: 1,
-        };
+            column: 1,
+
+            pointer: 0,
+            consumed_end: 0,
+
+            numeric_mode: NumericMode::default(),
+            dialect: Dialect::default(),
+            extensions: Extensions::new(),
+            alphabet: Alphabet::default(),
+            notation: Notation::default(),
+        }
+    }
+
+    /// Sets which number literals are accepted. This should match the mode of
+    /// the machines that will run the program.
+    pub fn with_numeric_mode(mut self, numeric_mode: NumericMode) -> Self {
+        self.numeric_mode = numeric_mode;
+
+        self
+    }
+
+    /// Sets which commands exist. Whitespace 0.2 has no `Copy` or `Slide`.
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+
+        self
+    }
 
-        obj.queue.retain(|&c| c.is_whitespace() && c != '\r');
+    /// Recognises the command patterns of `extensions` as extension
+    /// instructions.
+    pub fn with_extensions(mut self, extensions: &Extensions) -> Self {
+        self.extensions = extensions.clone();
 
-        return obj;
+        self
     }
 
+    /// Reads the source with `alphabet` standing in for space, tab and
+    /// linefeed. Must be set before anything is parsed.
+    pub fn with_alphabet(mut self, alphabet: Alphabet) -> Self {
+        self.alphabet = alphabet;
+
+        self
+    }
+
+    /// Reads the source in `notation`. STL notation takes the place of the
+    /// alphabet's characters, but keeps its policy for other whitespace. In
+    /// bracketed notation everything outside the brackets is a comment,
+    /// whitespace included. Must be set before anything is parsed.
+    pub fn with_notation(mut self, notation: Notation) -> Self {
+        self.notation = notation;
+
+        self
+    }
+
+    pub fn parse(&mut self) -> Result<Program, ParserError> {
+        let mut tokens = vec![];
+
+        while !self.is_end() {
+            tokens.push(self.read_token()?);
+        }
+
+        Program::link(tokens)
+    }
+
+    /// Parses the whole source without stopping at the first error.
+    ///
+    /// After an error the parser skips a character and tries again. Errors
+    /// raised before it manages to read another token are assumed to follow
+    /// from the first one and are not reported, unless the source could not
+    /// be read.
+    pub fn parse_all(&mut self) -> ParseOutput {
+        let mut tokens = vec![];
+        let mut errors = vec![];
+
+        let mut recovering = false;
+
+        while !self.is_end() {
+            match self.read_token() {
+                Ok(token) => {
+                    tokens.push(token);
+
+                    recovering = false;
+                },
+
+                Err(parser_error) => {
+                    if !recovering || matches!(parser_error.kind(), ParserErrorKind::Io { .. }) {
+                        errors.push(parser_error);
+                    };
+
+                    recovering = true;
+                },
+            };
+        }
+
+        errors.extend(Program::check_labels(&tokens));
+        errors.sort_by_key(|error| error.span().start);
+
+        ParseOutput {
+            tokens,
+            errors,
+        }
+    }
+
+    /// Reads a whole instruction. Its location covers the IMP, the command and
+    /// any argument.
     pub fn read_token(&mut self) -> Result<Token, ParserError> {
-        if let TokenType::Imp(imp_type) = self.read_imp()?.token_type {
-            self.read_command(imp_type)
-        } else {panic!("read_imp unexpectedly returned a non imp token")}
+        if let Some(token) = self.pending_token.take() {
+            return self.check_io(Ok(token));
+        };
+
+        let token = self.read_instruction();
+
+        self.check_io(token)
     }
 
-    fn read_number(&mut self) -> Result<i32, ParserError> {
-        let mut bin = String::new();
+    /// Reads the next IMP or command on its own, alternating between the two.
+    /// A command's location covers its argument but not its IMP.
+    pub fn read_lexeme(&mut self) -> Result<Token, ParserError> {
+        if let Some(token) = self.pending_token.take() {
+            return self.check_io(Ok(token));
+        };
 
-        let mut positive = true;
+        let token = match self.pending_imp.take() {
+            Some(imp) => self.read_command_after(&imp),
 
-        if let Some(c) = self.read_char() {
-            if c == ' ' {
-                positive = true;
-            } else if c == '\t' {
-                positive = false;
-            }
+            None => {
+                let imp = self.read_imp();
 
-            self.consume_char();
+                if let Ok(imp) = &imp {
+                    self.pending_imp = Some(imp.clone());
+                };
+
+                imp
+            },
+        };
+
+        self.check_io(token)
+    }
+
+    /// Iterates over the IMPs and commands of the source separately, see
+    /// [`Parser::read_lexeme`].
+    pub fn lexemes(&mut self) -> Lexemes<'_, R> {
+        Lexemes {
+            parser: self,
         }
+    }
+
+    fn read_instruction(&mut self) -> Result<Token, ParserError> {
+        let imp = match self.pending_imp.take() {
+            Some(imp) => imp,
+            None => self.read_imp()?,
+        };
+
+        let mut token = self.read_command_after(&imp)?;
+
+        token.location = self.span_from(imp.location);
+
+        Ok(token)
+    }
+
+    fn read_command_after(&mut self, imp: &Token) -> Result<Token, ParserError> {
+        if let TokenType::Imp(imp_type) = &imp.token_type {
+            let token = self.read_command(*imp_type)?;
+
+            Ok(Token {
+                location: self.span_from(token.location),
+
+                ..token
+            })
+        } else {panic!("read_command_after was given a non imp token")}
+    }
+
+    /// A source that fails to read looks like it ended early, so report the
+    /// underlying problem instead. Whitespace outside the alphabet is reported
+    /// before the token it was found in, which the next read then returns.
+    fn check_io(&mut self, token: Result<Token, ParserError>) -> Result<Token, ParserError> {
+        if let Some(io_error) = self.io_error.take() {
+            return Err(ParserError::new(ParserErrorKind::Io {
+                message: io_error.to_string(),
+            }, self.span(0), &[]));
+        };
+
+        let reached = self.queue.is_empty() || self.whitespace_errors.front().is_some_and(|error| error.span().start < self.consumed_end);
+
+        if reached {
+            if let Some(error) = self.whitespace_errors.pop_front() {
+                if let Ok(token) = token {
+                    self.pending_token = Some(token);
+                };
+
+                return Err(error);
+            };
+        };
+
+        token
+    }
+
+    fn read_number(&mut self) -> Result<Value, ParserError> {
+        let start = self.span(0);
+
+        let mut bin = String::new();
+
+        let positive = match self.read_char() {
+            Some(' ') => true,
+            Some('\t') => false,
+
+            _ => return Err(ParserError::new(ParserErrorKind::MissingSign, self.span_from(start), &[' ', '\t'])),
+        };
+
+        self.consume_char();
 
         while let Some(c) = self.read_char() {
             if c != ' ' && c != '\t' {break;};
@@ -86,28 +346,30 @@ impl Parser {
             self.consume_char();
         };
 
-        if let Some(c) = self.read_char() {
-            if c != '\n' {
-                return Err(ParserError::new(&format!("Number on line {} did not terminate with a linefeed", self.line)));
-            };
-
-            self.consume_char();
-        } else {
-            return Err(ParserError::new("Source unexpectedly ended while parsing number"));
+        if self.read_char() != Some('\n') {
+            return Err(ParserError::new(ParserErrorKind::UnterminatedNumber, self.span_from(start), &[' ', '\t', '\n']));
         };
 
-        if bin == "" {
-            return Ok(0);
+        self.consume_char();
+
+        if bin.is_empty() {
+            return Ok(Value::from(0));
         };
         
-        if let Ok(res) = i32::from_str_radix(&bin, 2) {
-            Ok(if positive {res} else {-res})
-        } else {
-            Err(ParserError::new(&format!("Unable to parse number on line {}", self.line)))
-        }
+        let res = Value::parse_bytes(bin.as_bytes(), 2).expect("number should only contain binary digits");
+
+        let res = if positive {res} else {-res};
+
+        if !self.numeric_mode.contains(&res) {
+            return Err(ParserError::new(ParserErrorKind::NumberOutOfRange { mode: self.numeric_mode }, self.span_from(start), &[]));
+        };
+
+        Ok(res)
     }
 
     fn read_label(&mut self) -> Result<String, ParserError> {
+        let start = self.span(0);
+
         let mut label = String::new();
 
         while let Some(c) = self.read_char() {
@@ -118,25 +380,25 @@ impl Parser {
             self.consume_char();
         };
 
-        if let Some(c) = self.read_char() {
-            if c != '\n' {
-                return Err(ParserError::new(&format!("Label on line {} did not terminate with a linefeed", self.line)));
-            };
-
-            self.consume_char();
-        } else {
-            return Err(ParserError::new("Source unexpectedly ended while parsing label"));
+        if self.read_char() != Some('\n') {
+            return Err(ParserError::new(ParserErrorKind::UnterminatedLabel, self.span_from(start), &[' ', '\t', '\n']));
         };
 
+        self.consume_char();
+
         Ok(label)
     }
 
     fn read_command(&mut self, imp_type: ImpType) -> Result<Token, ParserError> {
+        if let Some(token) = self.read_extension(imp_type) {
+            return Ok(token);
+        };
+
         match imp_type {
             ImpType::Stack => {
                 match self.read_char() {
                     Some(' ') => {
-                        let token = (make_token!(self, TokenType::Command(CommandType::Push(0)), self.pointer, self.pointer + 1) as Result<Token, ParserError>).unwrap();
+                        let token = (make_token!(self, TokenType::Command(CommandType::Push(Value::from(0))), self.pointer, self.pointer + 1) as Result<Token, ParserError>).unwrap();
 
                         Ok(Token {
                             token_type: TokenType::Command(CommandType::Push(self.read_number()?)),
@@ -150,14 +412,16 @@ impl Parser {
                             Some('\t') => make_token!(self, TokenType::Command(CommandType::Swap), self.pointer, self.pointer + 2),
                             Some('\n') => make_token!(self, TokenType::Command(CommandType::Disc), self.pointer, self.pointer + 2),
 
-                            _ => make_parser_error!(self, "Could not parse Stack command on line {}", self.line),
+                            _ => make_parser_error!(self, ParserErrorKind::UnknownCommand { imp: ImpType::Stack }, 2, [' ', '\t', '\n']),
                         }
                     },
 
+                    Some('\t') if !self.dialect.has_copy_and_slide() => make_parser_error!(self, ParserErrorKind::UnknownCommand { imp: ImpType::Stack }, 1, [' ', '\n']),
+
                     Some('\t') => {
                         match self.peek_char(1) {
                             Some(' ') => {
-                                let token = (make_token!(self, TokenType::Command(CommandType::Copy(0)), self.pointer, self.pointer + 2) as Result<Token, ParserError>).unwrap();
+                                let token = (make_token!(self, TokenType::Command(CommandType::Copy(Value::from(0))), self.pointer, self.pointer + 2) as Result<Token, ParserError>).unwrap();
         
                                 Ok(Token {
                                     token_type: TokenType::Command(CommandType::Copy(self.read_number()?)),
@@ -166,7 +430,7 @@ impl Parser {
                             },
 
                             Some('\n') => {
-                                let token = (make_token!(self, TokenType::Command(CommandType::Slide(0)), self.pointer, self.pointer + 2) as Result<Token, ParserError>).unwrap();
+                                let token = (make_token!(self, TokenType::Command(CommandType::Slide(Value::from(0))), self.pointer, self.pointer + 2) as Result<Token, ParserError>).unwrap();
         
                                 Ok(Token {
                                     token_type: TokenType::Command(CommandType::Slide(self.read_number()?)),
@@ -174,11 +438,11 @@ impl Parser {
                                 })
                             },
 
-                            _ => make_parser_error!(self, "Could not parse Stack command on line {}", self.line),
+                            _ => make_parser_error!(self, ParserErrorKind::UnknownCommand { imp: ImpType::Stack }, 2, [' ', '\n']),
                         }
                     },
 
-                    _ => make_parser_error!(self, "Could not parse Stack command on line {}", self.line),
+                    _ => make_parser_error!(self, ParserErrorKind::UnknownCommand { imp: ImpType::Stack }, 1, [' ', '\t', '\n']),
                 }
             },
 
@@ -190,7 +454,7 @@ impl Parser {
                             Some('\t') => make_token!(self, TokenType::Command(CommandType::Sub), self.pointer, self.pointer + 2),
                             Some('\n') => make_token!(self, TokenType::Command(CommandType::Mult), self.pointer, self.pointer + 2),
 
-                            _ => make_parser_error!(self, "Could not parse Arithmetic command on line {}", self.line),
+                            _ => make_parser_error!(self, ParserErrorKind::UnknownCommand { imp: ImpType::Arithmetic }, 2, [' ', '\t', '\n']),
                         }
                     },
 
@@ -199,11 +463,11 @@ impl Parser {
                             Some(' ') => make_token!(self, TokenType::Command(CommandType::IDiv), self.pointer, self.pointer + 2),
                             Some('\t') => make_token!(self, TokenType::Command(CommandType::Mod), self.pointer, self.pointer + 2),
 
-                            _ => make_parser_error!(self, "Could not parse Arithmetic command on line {}", self.line),
+                            _ => make_parser_error!(self, ParserErrorKind::UnknownCommand { imp: ImpType::Arithmetic }, 2, [' ', '\t']),
                         }
                     },
 
-                    _ => make_parser_error!(self, "Could not parse Arithmetic command on line {}", self.line),
+                    _ => make_parser_error!(self, ParserErrorKind::UnknownCommand { imp: ImpType::Arithmetic }, 1, [' ', '\t']),
                 }
             },
 
@@ -212,7 +476,7 @@ impl Parser {
                     Some(' ') => make_token!(self, TokenType::Command(CommandType::Store), self.pointer, self.pointer + 1),
                     Some('\t') => make_token!(self, TokenType::Command(CommandType::Retr), self.pointer, self.pointer + 1),
 
-                    _ => make_parser_error!(self, "Could not parse Heap command on line {}", self.line),
+                    _ => make_parser_error!(self, ParserErrorKind::UnknownCommand { imp: ImpType::Heap }, 1, [' ', '\t']),
                 }
             },
 
@@ -247,7 +511,7 @@ impl Parser {
                                 })
                             },
 
-                            _ => make_parser_error!(self, "Could not parse Flow command on line {}", self.line),
+                            _ => make_parser_error!(self, ParserErrorKind::UnknownCommand { imp: ImpType::Flow }, 2, [' ', '\t', '\n']),
                         }
                     },
 
@@ -273,7 +537,7 @@ impl Parser {
                             
                             Some('\n') => make_token!(self, TokenType::Command(CommandType::EndS), self.pointer, self.pointer + 2),
     
-                            _ => make_parser_error!(self, "Could not parse Flow command on line {}", self.line),
+                            _ => make_parser_error!(self, ParserErrorKind::UnknownCommand { imp: ImpType::Flow }, 2, [' ', '\t', '\n']),
                         }
                     },
 
@@ -281,11 +545,11 @@ impl Parser {
                         match self.peek_char(1) {
                             Some('\n') => make_token!(self, TokenType::Command(CommandType::EndP), self.pointer, self.pointer + 2),
     
-                            _ => make_parser_error!(self, "Could not parse Flow command on line {}", self.line),
+                            _ => make_parser_error!(self, ParserErrorKind::UnknownCommand { imp: ImpType::Flow }, 2, ['\n']),
                         }
                     },
 
-                    _ => make_parser_error!(self, "Could not parse Flow command on line {}", self.line),
+                    _ => make_parser_error!(self, ParserErrorKind::UnknownCommand { imp: ImpType::Flow }, 1, [' ', '\t', '\n']),
                 }
             },
 
@@ -296,7 +560,7 @@ impl Parser {
                             Some(' ') => make_token!(self, TokenType::Command(CommandType::OutC), self.pointer, self.pointer + 2),
                             Some('\t') => make_token!(self, TokenType::Command(CommandType::OutI), self.pointer, self.pointer + 2),
     
-                            _ => make_parser_error!(self, "Could not parse IO command on line {}", self.line),
+                            _ => make_parser_error!(self, ParserErrorKind::UnknownCommand { imp: ImpType::IO }, 2, [' ', '\t']),
                         }
                     },
 
@@ -305,16 +569,31 @@ impl Parser {
                             Some(' ') => make_token!(self, TokenType::Command(CommandType::ReadC), self.pointer, self.pointer + 2),
                             Some('\t') => make_token!(self, TokenType::Command(CommandType::ReadI), self.pointer, self.pointer + 2),
 
-                            _ => make_parser_error!(self, "Could not parse IO command on line {}", self.line),
+                            _ => make_parser_error!(self, ParserErrorKind::UnknownCommand { imp: ImpType::IO }, 2, [' ', '\t']),
                         }
                     },
 
-                    _ => make_parser_error!(self, "Could not parse IO command on line {}", self.line),
+                    _ => make_parser_error!(self, ParserErrorKind::UnknownCommand { imp: ImpType::IO }, 1, [' ', '\t']),
                 }
             },
         }
     }
 
+    fn read_extension(&mut self, imp_type: ImpType) -> Option<Token> {
+        let longest = self.extensions.iter().map(|extension| extension.opcode.pattern.len()).max()?;
+
+        let ahead: String = (0..longest).map_while(|index| self.peek_char(index)).collect();
+
+        let opcode = self.extensions.iter()
+            .map(|extension| &extension.opcode)
+            .find(|opcode| opcode.imp == imp_type && ahead.starts_with(&opcode.pattern))?
+            .clone();
+
+        let length = opcode.pattern.len();
+
+        (make_token!(self, TokenType::Command(CommandType::Extension(opcode)), self.pointer, self.pointer + length) as Result<Token, ParserError>).ok()
+    }
+
     fn read_imp(&mut self) -> Result<Token, ParserError> {
         match self.read_char() {
             Some(' ') => make_token!(self, TokenType::Imp(ImpType::Stack), self.pointer, self.pointer + 1),
@@ -325,35 +604,285 @@ impl Parser {
                 Some('\t') => make_token!(self, TokenType::Imp(ImpType::Heap), self.pointer, self.pointer + 2),
                 Some('\n') => make_token!(self, TokenType::Imp(ImpType::IO), self.pointer, self.pointer + 2),
                 
-                _ => make_parser_error!(self, "Could not parse IMP on line {}", self.line),
+                _ => make_parser_error!(self, ParserErrorKind::UnknownImp, 2, [' ', '\t', '\n']),
             },
 
-            _ => make_parser_error!(self, "Could not parse IMP on line {}", self.line),
+            _ => make_parser_error!(self, ParserErrorKind::UnknownImp, 1, [' ', '\t', '\n']),
         }
     }
 
-    fn read_char(&self) -> Option<char> {
-        self.queue.get(0).copied()
+    fn read_char(&mut self) -> Option<char> {
+        self.peek_char(0)
     }
 
-    fn peek_char(&self, amount: usize) -> Option<char> {
-        self.queue.get(amount).copied()
+    fn peek_char(&mut self, amount: usize) -> Option<char> {
+        self.fill(amount + 1);
+
+        self.queue.get(amount).map(|symbol| symbol.c)
     }
 
     fn consume_char(&mut self) -> Option<char> {
-        let res = self.read_char();
+        self.fill(1);
 
-        self.queue.pop_front();
+        let symbol = self.queue.pop_front()?;
 
         self.pointer += 1;
-        if let Some(c) = res {
-            if c == '\n' {self.line += 1;};
+        self.consumed_end = symbol.end;
+
+        Some(symbol.c)
+    }
+
+    /// Reads from the source until `count` symbols are waiting in the queue, or
+    /// until the source ends.
+    fn fill(&mut self, count: usize) {
+        while self.queue.len() < count && !self.at_eof {
+            let available = match self.reader.fill_buf() {
+                Ok(buffer) => buffer.len(),
+
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.io_error = Some(e);
+                    self.at_eof = true;
+
+                    break;
+                },
+            };
+
+            if available == 0 {
+                self.at_eof = true;
+                self.flush_partial();
+
+                break;
+            };
+
+            let mut used = 0;
+
+            while used < available && self.queue.len() < count {
+                let byte = self.reader.buffer()[used];
+
+                used += 1;
+
+                self.read_byte(byte);
+            }
+
+            self.reader.consume(used);
+        }
+    }
+
+    /// Decodes the source one byte at a time.
+    fn read_byte(&mut self, byte: u8) {
+        if !self.partial.is_empty() && byte & 0xC0 != 0x80 {
+            self.flush_partial();
+        };
+
+        self.offset += 1;
+
+        if self.partial.is_empty() && byte < 0x80 {
+            self.read_source_char(byte as char, self.offset - 1);
+
+            return;
+        };
+
+        if self.partial.is_empty() && !(0xC2..=0xF4).contains(&byte) {
+            // A stray continuation byte belongs to whatever came before it
+            if byte & 0xC0 != 0x80 {
+                self.column += 1;
+            };
+
+            return;
+        };
+
+        self.partial.push(byte);
+
+        let width = match self.partial[0] {
+            0xC2..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            _ => 4,
+        };
+
+        if self.partial.len() < width {
+            return;
+        };
+
+        match std::str::from_utf8(&self.partial).ok().and_then(|s| s.chars().next()) {
+            Some(c) => {
+                let start = self.offset - self.partial.len();
+
+                self.partial.clear();
+                self.read_source_char(c, start);
+            },
+
+            None => self.flush_partial(),
+        };
+    }
+
+    /// Gives up on a character that ended early or is not valid UTF-8. Its
+    /// bytes are a comment one column wide.
+    fn flush_partial(&mut self) {
+        if !self.partial.is_empty() {
+            self.partial.clear();
+            self.column += 1;
+        };
+    }
+
+    fn read_source_char(&mut self, c: char, offset: usize) {
+        let symbol = Symbol {
+            c,
+
+            offset,
+            end: self.offset,
+            line: self.line,
+            column: self.column,
+        };
+
+        let mut width = 1;
+
+        let alphabet = match self.notation {
+            Notation::Stl => Alphabet::new('S', 'T', 'L').with_other_whitespace(self.alphabet.other_whitespace()),
+            _ => self.alphabet,
+        };
+
+        if self.notation == Notation::Bracketed {
+            self.read_bracketed(c, symbol);
+        } else if let Some(mapped) = alphabet.symbol(c) {
+            self.queue.push_back(Symbol {
+                c: mapped,
+
+                ..symbol
+            });
+        } else if alphabet.is_other_whitespace(c) {
+            match alphabet.other_whitespace() {
+                WhitespacePolicy::Comment => {},
+                WhitespacePolicy::Ignore => width = 0,
+
+                WhitespacePolicy::Error => self.whitespace_errors.push_back(ParserError::new(ParserErrorKind::UnexpectedWhitespace {
+                    character: c,
+                }, Location {
+                    start: symbol.offset,
+                    end: symbol.end,
+
+                    line: symbol.line,
+                    column: symbol.column,
+                }, &[])),
+            };
+        };
+
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += width;
+        };
+    }
+
+    /// Collects the characters of a bracketed symbol such as `[Tab]`.
+    /// Anything that turns out not to be one is a comment.
+    fn read_bracketed(&mut self, c: char, symbol: Symbol) {
+        if c == '[' {
+            self.bracket = Some((symbol, String::from("[")));
+
+            return;
+        };
+
+        let Some((start, mut word)) = self.bracket.take() else {
+            return;
+        };
+
+        word.push(c);
+
+        if c != ']' {
+            if word.len() < "[Space]".len() {
+                self.bracket = Some((start, word));
+            };
+
+            return;
+        };
+
+        if let Some(mapped) = Notation::bracketed_symbol(&word) {
+            self.queue.push_back(Symbol {
+                c: mapped,
+
+                end: symbol.end,
+
+                ..start
+            });
+        };
+    }
+
+    /// The location of the next `length` symbols, cut short if the source
+    /// ends first.
+    fn span(&mut self, length: usize) -> Location {
+        self.fill(std::cmp::max(length, 1));
+
+        let first = self.queue.front().copied().unwrap_or(Symbol {
+            c: '\n',
+
+            offset: self.offset,
+            end: self.offset,
+            line: self.line,
+            column: self.column,
+        });
+
+        let end = match length.min(self.queue.len()).checked_sub(1).and_then(|index| self.queue.get(index)) {
+            Some(last) => last.end,
+            None => first.offset,
+        };
+
+        Location {
+            start: first.offset,
+            end,
+
+            line: first.line,
+            column: first.column,
+        }
+    }
+
+    /// The location from the start of `start` to the end of the last consumed
+    /// symbol.
+    fn span_from(&self, start: Location) -> Location {
+        Location {
+            end: std::cmp::max(start.start, self.consumed_end),
+
+            ..start
         }
+    }
 
-        return res;
+    /// Whether everything has been read. A read error that has not been
+    /// reported yet is not the end, so that the next read can report it.
+    pub fn is_end(&mut self) -> bool {
+        self.fill(1);
+
+        self.queue.is_empty() && self.pending_imp.is_none() && self.pending_token.is_none() && self.whitespace_errors.is_empty() && self.io_error.is_none()
+    }
+}
+
+/// Reads one instruction per item. After an error the parser skips a single
+/// character and carries on, like [`Parser::parse_all`] does.
+impl<R: Read> Iterator for Parser<R> {
+    type Item = Result<Token, ParserError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_end() {
+            return None;
+        };
+
+        Some(self.read_token())
     }
+}
+
+/// The iterator returned by [`Parser::lexemes`].
+pub struct Lexemes<'a, R> {
+    parser: &'a mut Parser<R>,
+}
+
+impl<R: Read> Iterator for Lexemes<'_, R> {
+    type Item = Result<Token, ParserError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.parser.is_end() {
+            return None;
+        };
 
-    pub fn is_end(&self) -> bool {
-        self.queue.is_empty()
+        Some(self.parser.read_lexeme())
     }
 }
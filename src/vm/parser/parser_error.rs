@@ -1,26 +1,114 @@
+use super::token::{ImpType, Location};
+use super::super::number::NumericMode;
+
 use std::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParserErrorKind {
+    UnknownImp,
+    UnknownCommand {
+        imp: ImpType,
+    },
+    MissingSign,
+    UnterminatedNumber,
+    UnterminatedLabel,
+    NumberOutOfRange {
+        mode: NumericMode,
+    },
+    UnexpectedEof,
+    UndefinedLabel {
+        label: String,
+    },
+    DuplicateLabel {
+        label: String,
+        previous: Location,
+    },
+    Io {
+        message: String,
+    },
+    UnexpectedWhitespace {
+        character: char,
+    },
+    UnexpectedImp {
+        imp: ImpType,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ParserError {
-    message: String,
+    kind: ParserErrorKind,
+
+    span: Location,
+    expected: Vec<char>,
 }
 
 impl ParserError {
-    pub fn new(message: &str) -> Self {
+    pub fn new(kind: ParserErrorKind, span: Location, expected: &[char]) -> Self {
         Self {
-            message: message.to_string(),
+            kind,
+
+            span,
+            expected: expected.to_vec(),
         }
     }
+
+    pub fn kind(&self) -> &ParserErrorKind {
+        &self.kind
+    }
+
+    /// The part of the source the error is about.
+    pub fn span(&self) -> &Location {
+        &self.span
+    }
+
+    /// The characters that would have been valid where parsing failed. Empty
+    /// for errors that are not about a single unexpected character.
+    pub fn expected(&self) -> &[char] {
+        &self.expected
+    }
 }
 
 impl fmt::Display for ParserError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.message)
+        let line = format!("{}, column {}", self.span.line, self.span.column);
+
+        match &self.kind {
+            ParserErrorKind::UnknownImp => write!(f, "Could not parse IMP on line {}", line)?,
+            ParserErrorKind::UnknownCommand { imp } => write!(f, "Could not parse {:?} command on line {}", imp, line)?,
+            ParserErrorKind::MissingSign => write!(f, "Number on line {} did not start with a sign", line)?,
+            ParserErrorKind::UnterminatedNumber => write!(f, "Number on line {} did not terminate with a linefeed", line)?,
+            ParserErrorKind::UnterminatedLabel => write!(f, "Label on line {} did not terminate with a linefeed", line)?,
+            ParserErrorKind::NumberOutOfRange { mode } => write!(f, "Number on line {} does not fit in a {} integer", line, mode)?,
+            ParserErrorKind::UnexpectedEof => write!(f, "Source unexpectedly ended on line {}", line)?,
+            ParserErrorKind::UndefinedLabel { label } => write!(f, "Label {} on line {} is never defined", display_label(label), line)?,
+            ParserErrorKind::DuplicateLabel { label, previous } => write!(f, "Label {} on line {} was already defined on line {}", display_label(label), line, previous.line)?,
+            ParserErrorKind::Io { message } => write!(f, "Could not read source on line {}: {}", line, message)?,
+            ParserErrorKind::UnexpectedWhitespace { character } => write!(f, "Whitespace character U+{:04X} on line {} is not part of the alphabet", *character as u32, line)?,
+            ParserErrorKind::UnexpectedImp { imp } => write!(f, "{:?} IMP on line {} is not part of a whole instruction", imp, line)?,
+        };
+
+        if !self.expected.is_empty() {
+            let expected: Vec<&str> = self.expected.iter().map(|&c| display_char(c)).collect();
+
+            write!(f, " (expected {})", expected.join(", "))?;
+        };
+
+        Ok(())
     }
 }
 
-impl std::error::Error for ParserError {
-    fn description(&self) -> &str {
-        &self.message
+impl std::error::Error for ParserError {}
+
+/// Spells a label out with `S` and `T`, since the raw spaces and tabs are invisible.
+fn display_label(label: &str) -> String {
+    label.chars().map(|c| if c == ' ' {'S'} else {'T'}).collect()
+}
+
+fn display_char(c: char) -> &'static str {
+    match c {
+        ' ' => "space",
+        '\t' => "tab",
+        '\n' => "linefeed",
+        _ => "other",
     }
 }
\ No newline at end of file
@@ -0,0 +1,87 @@
+use super::parser::Parser;
+use super::parser_error::ParserError;
+use super::token::Token;
+
+use std::fmt;
+
+/// One instruction along with the comments in front of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxNode {
+    /// Everything between the previous instruction and this one.
+    pub leading_trivia: String,
+    /// The source of the instruction itself, including any comments that
+    /// appear between its characters.
+    pub text: String,
+
+    pub token: Token,
+}
+
+/// A parse that keeps every character of the source, so that a program can
+/// be rewritten and printed back out without losing its comments.
+///
+/// Printing a tree with [`fmt::Display`] reproduces the source it was built
+/// from byte for byte.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyntaxTree {
+    pub nodes: Vec<SyntaxNode>,
+    /// Everything after the last instruction.
+    pub trailing_trivia: String,
+}
+
+impl SyntaxTree {
+    pub fn parse(source: &str) -> Result<Self, ParserError> {
+        Self::parse_with(source, |parser| parser)
+    }
+
+    /// Parses `source` with a parser that `configure` has set up, such as one
+    /// with another alphabet, notation or set of extensions.
+    pub fn parse_with(source: &str, configure: impl FnOnce(Parser) -> Parser) -> Result<Self, ParserError> {
+        let mut parser = configure(Parser::new(source.to_string()));
+        let mut tokens = vec![];
+
+        while !parser.is_end() {
+            tokens.push(parser.read_token()?);
+        }
+
+        Ok(Self::from_tokens(source, tokens))
+    }
+
+    /// Builds a tree from tokens that were read from `source`, in order, for
+    /// tokens that come from somewhere other than [`SyntaxTree::parse_with`].
+    pub fn from_tokens(source: &str, tokens: Vec<Token>) -> Self {
+        let mut nodes = Vec::with_capacity(tokens.len());
+        let mut position = 0;
+
+        for token in tokens {
+            let location = token.location;
+
+            nodes.push(SyntaxNode {
+                leading_trivia: source[position..location.start].to_string(),
+                text: source[location.start..location.end].to_string(),
+
+                token,
+            });
+
+            position = location.end;
+        }
+
+        Self {
+            nodes,
+            trailing_trivia: source[position..].to_string(),
+        }
+    }
+
+    pub fn tokens(&self) -> impl Iterator<Item = &Token> {
+        self.nodes.iter().map(|node| &node.token)
+    }
+}
+
+impl fmt::Display for SyntaxTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for node in &self.nodes {
+            write!(f, "{}{}", node.leading_trivia, node.text)?;
+        }
+
+        write!(f, "{}", self.trailing_trivia)
+    }
+}
\ No newline at end of file
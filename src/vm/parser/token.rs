@@ -1,4 +1,8 @@
-#[derive(Debug, PartialEq)]
+use super::super::number::Value;
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ImpType {
     Stack,
     Arithmetic,
@@ -7,14 +11,14 @@ pub enum ImpType {
     IO,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum CommandType {
-    Push(i32),
+    Push(Value),
     Dup,
-    Copy(i32),
+    Copy(Value),
     Swap,
     Disc,
-    Slide(i32),
+    Slide(Value),
 
     Add,
     Sub,
@@ -36,23 +40,61 @@ pub enum CommandType {
     OutI,
     ReadC,
     ReadI,
+
+    Extension(Opcode),
+}
+
+/// The characters that follow an IMP to select a command, for commands that
+/// are not part of the language but added by the host.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Opcode {
+    pub imp: ImpType,
+    /// Spaces, tabs and linefeeds.
+    pub pattern: String,
+}
+
+impl Opcode {
+    pub fn new(imp: ImpType, pattern: &str) -> Self {
+        Self {
+            imp,
+            pattern: pattern.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} ", self.imp)?;
+
+        for c in self.pattern.chars() {
+            write!(f, "{}", match c {' ' => 'S', '\t' => 'T', '\n' => 'L', _ => '?'})?;
+        }
+
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TokenType {
     Imp(ImpType),
     Command(CommandType),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A span of the original source.
+///
+/// `start` and `end` are byte offsets, with `end` exclusive. `line` and
+/// `column` are where the span starts, both counted from 1, with columns
+/// counted in characters.
 pub struct Location {
     pub start: usize,
     pub end: usize,
 
     pub line: usize,
+    pub column: usize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Token {
     pub token_type: TokenType,
 
@@ -0,0 +1,242 @@
+use super::number::Value;
+use super::parser::parser_error::{ParserError, ParserErrorKind};
+use super::parser::token::{CommandType, Location, Opcode, Token, TokenType};
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instruction {
+    Push(Value),
+    Dup,
+    Copy(Value),
+    Swap,
+    Disc,
+    Slide(Value),
+
+    Add,
+    Sub,
+    Mult,
+    IDiv,
+    Mod,
+    Store,
+    Retr,
+
+    Labl(String),
+    /// Jump targets are the index of the `Labl` instruction they refer to.
+    Call(usize),
+    Jump(usize),
+    JumpZ(usize),
+    JumpN(usize),
+    EndS,
+    EndP,
+
+    OutC,
+    OutI,
+    ReadC,
+    ReadI,
+
+    Extension(Opcode),
+}
+
+/// A parsed program, independent of the source it was read from.
+///
+/// Source locations are kept in a separate table indexed the same way as the
+/// instructions, so tools that only care about execution never see them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    instructions: Vec<Instruction>,
+    labels: HashMap<String, usize>,
+
+    locations: Vec<Location>,
+}
+
+impl Program {
+    /// Resolves every label reference in `tokens` to an instruction index.
+    ///
+    /// Fails on the first jump to a label that is never defined, or on a label
+    /// that is defined more than once. The tokens must be whole instructions,
+    /// as read by [`Parser::read_token`](super::parser::parser::Parser::read_token),
+    /// so a separate IMP token is an error too.
+    pub fn link(tokens: Vec<Token>) -> Result<Self, ParserError> {
+        let imp = tokens.iter().find_map(|token| match token.token_type {
+            TokenType::Imp(imp) => Some((imp, token.location)),
+            TokenType::Command(_) => None,
+        });
+
+        if let Some((imp, location)) = imp {
+            return Err(ParserError::new(ParserErrorKind::UnexpectedImp { imp }, location, &[]));
+        };
+
+        let (labels, errors) = resolve_labels(&tokens);
+
+        if let Some(error) = errors.into_iter().next() {
+            return Err(error);
+        };
+
+        let mut instructions = Vec::with_capacity(tokens.len());
+        let mut locations = Vec::with_capacity(tokens.len());
+
+        for token in tokens {
+            let TokenType::Command(command) = token.token_type else {
+                unreachable!("IMP tokens were rejected above")
+            };
+
+            let target = |label: &str| labels[label];
+
+            instructions.push(match command {
+                CommandType::Push(n) => Instruction::Push(n),
+                CommandType::Dup => Instruction::Dup,
+                CommandType::Copy(n) => Instruction::Copy(n),
+                CommandType::Swap => Instruction::Swap,
+                CommandType::Disc => Instruction::Disc,
+                CommandType::Slide(n) => Instruction::Slide(n),
+
+                CommandType::Add => Instruction::Add,
+                CommandType::Sub => Instruction::Sub,
+                CommandType::Mult => Instruction::Mult,
+                CommandType::IDiv => Instruction::IDiv,
+                CommandType::Mod => Instruction::Mod,
+                CommandType::Store => Instruction::Store,
+                CommandType::Retr => Instruction::Retr,
+
+                CommandType::Labl(label) => Instruction::Labl(label),
+                CommandType::Call(label) => Instruction::Call(target(&label)),
+                CommandType::Jump(label) => Instruction::Jump(target(&label)),
+                CommandType::JumpZ(label) => Instruction::JumpZ(target(&label)),
+                CommandType::JumpN(label) => Instruction::JumpN(target(&label)),
+                CommandType::EndS => Instruction::EndS,
+                CommandType::EndP => Instruction::EndP,
+
+                CommandType::OutC => Instruction::OutC,
+                CommandType::OutI => Instruction::OutI,
+                CommandType::ReadC => Instruction::ReadC,
+                CommandType::ReadI => Instruction::ReadI,
+
+                CommandType::Extension(opcode) => Instruction::Extension(opcode),
+            });
+
+            locations.push(token.location);
+        }
+
+        Ok(Self {
+            instructions,
+            labels,
+
+            locations,
+        })
+    }
+
+    /// Finds every undefined or duplicate label in `tokens`, in source order.
+    pub fn check_labels(tokens: &[Token]) -> Vec<ParserError> {
+        resolve_labels(tokens).1
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    pub fn labels(&self) -> &HashMap<String, usize> {
+        &self.labels
+    }
+
+    pub fn locations(&self) -> &[Location] {
+        &self.locations
+    }
+
+    pub fn label(&self, label: &str) -> Option<usize> {
+        self.labels.get(label).copied()
+    }
+
+    pub fn location(&self, index: usize) -> Option<&Location> {
+        self.locations.get(index)
+    }
+
+    /// The command the instruction at `index` was linked from, with jumps
+    /// naming the label they go to.
+    pub fn command(&self, index: usize) -> Option<CommandType> {
+        let label = |target: usize| match &self.instructions[target] {
+            Instruction::Labl(label) => label.clone(),
+            _ => panic!("jump target should be a label"),
+        };
+
+        Some(match self.instructions.get(index)? {
+            Instruction::Push(n) => CommandType::Push(n.clone()),
+            Instruction::Dup => CommandType::Dup,
+            Instruction::Copy(n) => CommandType::Copy(n.clone()),
+            Instruction::Swap => CommandType::Swap,
+            Instruction::Disc => CommandType::Disc,
+            Instruction::Slide(n) => CommandType::Slide(n.clone()),
+
+            Instruction::Add => CommandType::Add,
+            Instruction::Sub => CommandType::Sub,
+            Instruction::Mult => CommandType::Mult,
+            Instruction::IDiv => CommandType::IDiv,
+            Instruction::Mod => CommandType::Mod,
+            Instruction::Store => CommandType::Store,
+            Instruction::Retr => CommandType::Retr,
+
+            Instruction::Labl(label) => CommandType::Labl(label.clone()),
+            Instruction::Call(target) => CommandType::Call(label(*target)),
+            Instruction::Jump(target) => CommandType::Jump(label(*target)),
+            Instruction::JumpZ(target) => CommandType::JumpZ(label(*target)),
+            Instruction::JumpN(target) => CommandType::JumpN(label(*target)),
+            Instruction::EndS => CommandType::EndS,
+            Instruction::EndP => CommandType::EndP,
+
+            Instruction::OutC => CommandType::OutC,
+            Instruction::OutI => CommandType::OutI,
+            Instruction::ReadC => CommandType::ReadC,
+            Instruction::ReadI => CommandType::ReadI,
+
+            Instruction::Extension(opcode) => CommandType::Extension(opcode.clone()),
+        })
+    }
+
+    /// Every instruction as the command it was linked from, see
+    /// [`Program::command`].
+    pub fn commands(&self) -> impl Iterator<Item = CommandType> + '_ {
+        (0..self.len()).map(|index| self.command(index).expect("index should be in range"))
+    }
+
+    pub fn len(&self) -> usize {
+        self.instructions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
+}
+
+/// Maps each label to the index of its first definition, collecting an error
+/// for every later definition and for every reference to a missing label.
+fn resolve_labels(tokens: &[Token]) -> (HashMap<String, usize>, Vec<ParserError>) {
+    let mut labels = HashMap::<String, usize>::new();
+    let mut errors = vec![];
+
+    for (index, token) in tokens.iter().enumerate() {
+        if let TokenType::Command(CommandType::Labl(label)) = &token.token_type {
+            if let Some(&previous) = labels.get(label) {
+                errors.push(ParserError::new(ParserErrorKind::DuplicateLabel {
+                    label: label.to_string(),
+                    previous: tokens[previous].location,
+                }, token.location, &[]));
+            } else {
+                labels.insert(label.to_string(), index);
+            };
+        }
+    }
+
+    for token in tokens {
+        if let TokenType::Command(CommandType::Call(label) | CommandType::Jump(label) | CommandType::JumpZ(label) | CommandType::JumpN(label)) = &token.token_type {
+            if !labels.contains_key(label) {
+                errors.push(ParserError::new(ParserErrorKind::UndefinedLabel {
+                    label: label.to_string(),
+                }, token.location, &[]));
+            };
+        }
+    }
+
+    errors.sort_by_key(|error| error.span().start);
+
+    (labels, errors)
+}
@@ -1,321 +1,638 @@
-use super::parser::{self as parser_mod, parser};
+use super::vm_error::{VMError, VMErrorKind};
+use super::dialect::Dialect;
+use super::extensions::{ExtensionContext, Extensions};
+use super::limits::{Limit, Limits};
 
-use super::vm_error::VMError;
-use parser_mod::parser_error::ParserError;
+use super::number::{NumericMode, Value};
+use super::program::{Instruction, Program};
 
-use parser_mod::token::TokenType;
-use parser_mod::token::CommandType;
+use num_integer::Integer;
+use num_traits::{Signed, ToPrimitive, Zero};
 
-use std::io::{self, Read};
+use std::io::{self, Read, Write};
 use std::collections::HashMap;
+use std::sync::Arc;
+
+/// How many values from the top of the stack are kept in a [`VMError`].
+const STACK_SNAPSHOT: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Running,
+    Halted,
+    /// A read could not complete because the input reported
+    /// [`io::ErrorKind::WouldBlock`]; stepping again retries it.
+    WaitingForInput,
+    /// The instruction budget ran out; adding fuel lets execution continue.
+    OutOfFuel,
+    Errored,
+}
 
-pub struct VirtualMachine<'a> {
-    parser: &'a mut parser::Parser,
+/// Executes a [`Program`].
+///
+/// The program is shared, so any number of machines can run the same parse,
+/// each with its own stack and heap. Input is read from `I` and output written
+/// to `O`, which default to the process's stdin and stdout.
+pub struct VirtualMachine<I = io::Stdin, O = io::Stdout> {
+    program: Arc<Program>,
 
-    collection: Vec<parser_mod::token::Token>,
-    labels: HashMap<String, usize>,
+    input: I,
+    output: O,
 
     subroutine_stack: Vec<usize>,
-    stack: Vec<i32>,
-    heap: HashMap<u32, i32>,
+    stack: Vec<Value>,
+    heap: HashMap<Value, Value>,
 
     pointer: usize,
+    status: Status,
+    fuel: Option<u64>,
+    limits: Limits,
+    numeric_mode: NumericMode,
+    dialect: Dialect,
+    extensions: Extensions,
+
+    input_line: Vec<u8>,
+}
 
-    parsed: bool,
+impl VirtualMachine {
+    pub fn new(program: impl Into<Arc<Program>>) -> Self {
+        Self::with_io(program, io::stdin(), io::stdout())
+    }
 }
 
-impl<'a> VirtualMachine<'a> {
-    pub fn new(parser: &'a mut parser::Parser) -> Self {
+impl<I: Read, O: Write> VirtualMachine<I, O> {
+    pub fn with_io(program: impl Into<Arc<Program>>, input: I, output: O) -> Self {
         Self {
-            parser,
-            collection: vec![],
-            labels: HashMap::<String, usize>::new(),
+            program: program.into(),
+
+            input,
+            output,
             
             subroutine_stack: vec![],
             stack: vec![],
-            heap: HashMap::<u32, i32>::new(),
+            heap: HashMap::<Value, Value>::new(),
 
             pointer: 0,
-
-            parsed: false,
+            status: Status::Running,
+            fuel: None,
+            limits: Limits::default(),
+            numeric_mode: NumericMode::default(),
+            dialect: Dialect::default(),
+            extensions: Extensions::new(),
+
+            input_line: vec![],
         }
     }
 
-    pub fn parse(&mut self) -> Result<(), ParserError> {
-        if self.parsed {
-            return Err(ParserError::new("Already parsed source"));
+    /// Limits how many more instructions may be executed, or lifts the limit
+    /// with `None`. Machines start without a limit.
+    pub fn set_fuel(&mut self, fuel: Option<u64>) {
+        self.fuel = fuel;
+
+        if self.status == Status::OutOfFuel {
+            self.status = Status::Running;
+        };
+    }
+
+    /// Allows `amount` more instructions on top of the remaining budget.
+    pub fn add_fuel(&mut self, amount: u64) {
+        if let Some(fuel) = self.fuel {
+            self.set_fuel(Some(fuel.saturating_add(amount)));
         };
+    }
 
-        self.parsed = true;
+    pub fn fuel(&self) -> Option<u64> {
+        self.fuel
+    }
 
-        while !self.parser.is_end() {
-            self.collection.push(self.parser.read_token()?);
-        }
+    /// Chooses how arithmetic behaves. The program should have been parsed
+    /// with the same mode so that its literals fit.
+    pub fn with_numeric_mode(mut self, numeric_mode: NumericMode) -> Self {
+        self.numeric_mode = numeric_mode;
 
-        for (index, token) in self.collection.iter().enumerate() {
-            if let TokenType::Command(CommandType::Labl(label)) = &token.token_type {
-                self.labels.insert(label.to_string(), index);
-            }
-        }
+        self
+    }
+
+    pub fn numeric_mode(&self) -> NumericMode {
+        self.numeric_mode
+    }
+
+    /// Chooses whose semantics to follow. The program should have been parsed
+    /// with the same dialect.
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+
+        self
+    }
+
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    /// Provides the extension instructions the program was parsed with.
+    pub fn with_extensions(mut self, extensions: Extensions) -> Self {
+        self.extensions = extensions;
+
+        self
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Clears the stacks and heap and moves back to the first instruction.
+    ///
+    /// The remaining fuel is left as it is.
+    pub fn reset(&mut self) {
+        self.subroutine_stack.clear();
+        self.stack.clear();
+        self.heap.clear();
+
+        self.pointer = 0;
+        self.status = Status::Running;
+
+        self.input_line.clear();
+    }
 
-        return Ok(());
+    /// Runs until the program halts, needs more input, runs out of fuel, or fails.
+    pub fn run(&mut self) -> Result<Status, VMError> {
+        self.run_until(|_| false)
     }
 
-    //TODO: make a separate command struct instead of using the token struct
-    pub fn run(&mut self) -> Result<(), VMError> {
-        while self.pointer != self.collection.len() {
-            if self.pointer >= self.collection.len() {
-                return Err(VMError::new(&format!("Program pointer out of range (at {})", self.pointer)));
+    /// Executes at most `steps` instructions.
+    pub fn run_for(&mut self, steps: usize) -> Result<Status, VMError> {
+        let mut remaining = steps;
+
+        self.run_until(|_| {
+            if remaining == 0 {
+                return true;
             };
 
-            match self.collection[self.pointer].token_type {
-                TokenType::Command(CommandType::Push(ref n)) => {
-                    self.stack.push(*n);
-                },
+            remaining -= 1;
 
-                TokenType::Command(CommandType::Dup) => {
-                    self.stack.push(self.get_stack(0)?);
-                },
+            false
+        })
+    }
 
-                TokenType::Command(CommandType::Copy(ref n)) => {
-                    self.stack.push(self.get_stack(*n as usize)?);
-                },
+    /// Runs until `predicate` returns true for the machine about to execute its
+    /// next instruction, or until the program stops running on its own.
+    pub fn run_until(&mut self, mut predicate: impl FnMut(&Self) -> bool) -> Result<Status, VMError> {
+        let result = loop {
+            if predicate(self) {
+                break Ok(self.status);
+            };
 
-                TokenType::Command(CommandType::Swap) => {
-                    let n1 = self.pop_stack(0)?;
-                    let n2 = self.pop_stack(0)?;
+            match self.step() {
+                Ok(Status::Running) => {},
+                res => break res,
+            };
+        };
 
-                    self.stack.push(n1);
-                    self.stack.push(n2);
-                },
+        if let Err(kind) = self.flush_output() {
+            return Err(self.error(kind));
+        };
 
-                TokenType::Command(CommandType::Disc) => {
-                    self.pop_stack(0)?;
-                },
+        result
+    }
 
-                TokenType::Command(CommandType::Slide(ref n)) => {
-                    let n = *n;
+    /// Executes the instruction at the program pointer.
+    ///
+    /// A machine that is waiting for input retries the read; a halted or
+    /// errored machine stays that way until it is reset.
+    pub fn step(&mut self) -> Result<Status, VMError> {
+        if self.status == Status::Halted || self.status == Status::Errored {
+            return Ok(self.status);
+        };
 
-                    let top = self.pop_stack(0)?;
+        if self.pointer >= self.program.len() {
+            if self.dialect.is_strict() {
+                self.status = Status::Errored;
 
-                    for _ in 0..n {
-                        self.pop_stack(0)?;
-                    };
+                return Err(self.error(VMErrorKind::MissingEnd));
+            };
 
-                    self.stack.push(top);
-                },
+            self.status = Status::Halted;
 
+            return Ok(self.status);
+        };
 
-                TokenType::Command(CommandType::Add) => {
-                    let n1 = self.pop_stack(0)?;
-                    let n2 = self.pop_stack(0)?;
+        if self.fuel == Some(0) {
+            self.status = Status::OutOfFuel;
 
-                    self.stack.push(n2 + n1);
-                },
+            return Ok(self.status);
+        };
 
-                TokenType::Command(CommandType::Sub) => {
-                    let n1 = self.pop_stack(0)?;
-                    let n2 = self.pop_stack(0)?;
+        match self.execute() {
+            Ok(status) => {
+                if status != Status::WaitingForInput {
+                    self.fuel = self.fuel.map(|fuel| fuel - 1);
+                };
 
-                    self.stack.push(n2 - n1);
-                },
+                self.status = status;
 
-                TokenType::Command(CommandType::Mult) => {
-                    let n1 = self.pop_stack(0)?;
-                    let n2 = self.pop_stack(0)?;
+                Ok(status)
+            },
 
-                    self.stack.push(n2 * n1);
-                },
+            Err(kind) => {
+                self.status = Status::Errored;
 
-                TokenType::Command(CommandType::IDiv) => {
-                    let n1 = self.pop_stack(0)?;
-                    let n2 = self.pop_stack(0)?;
+                Err(self.error(kind))
+            },
+        }
+    }
 
-                    self.stack.push(n2 / n1);
-                },
+    fn execute(&mut self) -> Result<Status, VMErrorKind> {
+        match self.program.instructions()[self.pointer] {
+            Instruction::Push(ref n) => {
+                self.push_number(n.clone())?;
+            },
 
-                TokenType::Command(CommandType::Mod) => {
-                    let n1 = self.pop_stack(0)?;
-                    let n2 = self.pop_stack(0)?;
+            Instruction::Dup => {
+                self.push_stack(self.get_stack(0)?)?;
+            },
 
-                    self.stack.push(n2 % n1);
-                },
+            Instruction::Copy(ref n) => {
+                let Some(n) = n.to_usize().and_then(|index| self.get_stack(index).ok()) else {
+                    return Err(VMErrorKind::InvalidStackIndex(n.clone()));
+                };
 
+                self.push_stack(n)?;
+            },
 
-                TokenType::Command(CommandType::Store) => {
-                    let n1 = self.pop_stack(0)?;
-                    let n2 = self.pop_stack(0)?;
-                    
-                    if n2 < 0 {
-                        return Err(VMError::new("Heap index can not be negative"));
-                    };
-                    
-                    self.heap.insert(n2 as u32, n1);
-                },
-
-                TokenType::Command(CommandType::Retr) => {
-                    let n1 = self.pop_stack(0)?;
-                    
-                    if n1 < 0 {
-                        return Err(VMError::new("Heap index can not be negative"));
-                    }
-                    
-                    let res = self.heap.get(&(n1 as u32));
-
-                    if let None = res {
-                        self.heap.insert(n1 as u32, 0);
-
-                        self.stack.push(0);
-                    }
-                    else
-                    {
-                        self.stack.push(*res.unwrap());
-                    };
-                },
+            Instruction::Swap => {
+                self.get_stack(1)?;
 
+                let length = self.stack.len();
 
-                TokenType::Command(CommandType::Labl(_)) => {},
+                self.stack.swap(length - 1, length - 2);
+            },
 
-                TokenType::Command(CommandType::Call(ref label)) => {
-                    self.subroutine_stack.push(self.pointer);
+            Instruction::Disc => {
+                self.pop_stack(0)?;
+            },
 
-                    self.pointer = self.get_label(label)? + 1;
+            Instruction::Slide(ref n) => {
+                let n = if n.is_negative() {0} else {n.to_usize().unwrap_or(usize::MAX)};
 
-                    continue;
-                },
+                let top = self.get_stack(0)?;
 
-                TokenType::Command(CommandType::Jump(ref label)) => {
-                    //+ 1 because labels will attempt to skip commands if it's a subroutine
-                    self.pointer = self.get_label(label)? + 1;
+                if n >= self.stack.len() {
+                    return Err(VMErrorKind::StackUnderflow);
+                };
 
-                    continue;
-                },
+                self.drop_stack(n + 1);
+                self.push_stack(top)?;
+            },
 
-                TokenType::Command(CommandType::JumpZ(ref label)) => {
-                    let label = label.clone();
 
-                    if self.pop_stack(0)? == 0 {
-                        self.pointer = self.get_label(&label)? + 1;
+            Instruction::Add => {
+                self.arithmetic(|n2, n1| Ok(n2 + n1))?;
+            },
 
-                        continue;
-                    };
-                },
+            Instruction::Sub => {
+                self.arithmetic(|n2, n1| Ok(n2 - n1))?;
+            },
 
-                TokenType::Command(CommandType::JumpN(ref label)) => {
-                    let label = label.clone();
-                    
-                    if self.pop_stack(0)? < 0 {
-                        self.pointer = self.get_label(&label)? + 1;
+            Instruction::Mult => {
+                self.arithmetic(|n2, n1| Ok(n2 * n1))?;
+            },
 
-                        continue;
-                    };
-                },
+            Instruction::IDiv => {
+                let strict = self.dialect.is_strict();
 
-                TokenType::Command(CommandType::EndS) => {
-                    if let Some(addr) = self.subroutine_stack.pop() {
-                        //+ 1 so it doesn't jump to the call command
-                        self.pointer = addr + 1;
-                    };
+                self.arithmetic(|n2, n1| match n1.is_zero() {
+                    true => Err(VMErrorKind::DivisionByZero),
+                    false => Ok(if strict {n2.div_floor(&n1)} else {n2 / n1}),
+                })?;
+            },
 
-                    continue;
-                },
+            Instruction::Mod => {
+                let strict = self.dialect.is_strict();
 
-                TokenType::Command(CommandType::EndP) => {
-                    break;
-                },
+                self.arithmetic(|n2, n1| match n1.is_zero() {
+                    true => Err(VMErrorKind::DivisionByZero),
+                    false => Ok(if strict {n2.mod_floor(&n1)} else {n2 % n1}),
+                })?;
+            },
 
 
-                TokenType::Command(CommandType::OutC) => {
-                    print!("{}", char::from_u32(self.pop_stack(0)? as u32).unwrap());
-                },
+            Instruction::Store => {
+                let n1 = self.get_stack(0)?;
+                let n2 = self.get_stack(1)?;
 
-                TokenType::Command(CommandType::OutI) => {
-                    print!("{}", self.pop_stack(0)?);
-                },
+                if n2.is_negative() {
+                    return Err(VMErrorKind::NegativeHeapAddress(n2));
+                };
 
-                TokenType::Command(CommandType::ReadC) => {
-                    let loc = self.get_stack(0)?;
+                self.store_heap(n2, n1)?;
+                self.drop_stack(2);
+            },
 
-                    if loc < 0 {
-                        return Err(VMError::new("Heap index can not be negative"));
-                    };
+            Instruction::Retr => {
+                let n1 = self.get_stack(0)?;
 
-                    let mut input: [u8; 1] = [0];
+                if n1.is_negative() {
+                    return Err(VMErrorKind::NegativeHeapAddress(n1));
+                }
 
-                    if let Err(_) = io::stdin().read_exact(&mut input) {
-                        return Err(VMError::new("Could not read from user input"));
-                    };
+                let n = self.heap.get(&n1).cloned().unwrap_or_default();
 
-                    self.heap.insert(loc as u32, input[0] as i32);
-                },
+                self.drop_stack(1);
+                self.push_stack(n)?;
+            },
 
-                TokenType::Command(CommandType::ReadI) => {
-                    let loc = self.get_stack(0)?;
 
-                    if loc < 0 {
-                        return Err(VMError::new("Heap index can not be negative"));
+            Instruction::Labl(_) => {},
+
+            Instruction::Call(target) => {
+                if let Some(max) = self.limits.max_call_depth {
+                    if self.subroutine_stack.len() >= max {
+                        return Err(VMErrorKind::LimitExceeded { limit: Limit::CallDepth, max });
                     };
+                };
+
+                self.subroutine_stack.push(self.pointer);
+
+                self.pointer = target;
+
+                return Ok(Status::Running);
+            },
+
+            Instruction::Jump(target) => {
+                self.pointer = target;
+
+                return Ok(Status::Running);
+            },
+
+            Instruction::JumpZ(target) => {
+                if self.pop_stack(0)?.is_zero() {
+                    self.pointer = target;
+
+                    return Ok(Status::Running);
+                };
+            },
+
+            Instruction::JumpN(target) => {
+                if self.pop_stack(0)?.is_negative() {
+                    self.pointer = target;
+
+                    return Ok(Status::Running);
+                };
+            },
+
+            Instruction::EndS => {
+                match self.subroutine_stack.pop() {
+                    //+ 1 so it doesn't jump to the call command
+                    Some(addr) => self.pointer = addr + 1,
+
+                    None if self.dialect.is_strict() => return Err(VMErrorKind::ReturnOutsideSubroutine),
+                    None => self.pointer += 1,
+                };
+
+                return Ok(Status::Running);
+            },
+
+            Instruction::EndP => {
+                return Ok(Status::Halted);
+            },
+
+
+            Instruction::OutC => {
+                let n = self.get_stack(0)?;
+
+                let Some(c) = n.to_u32().and_then(char::from_u32) else {
+                    return Err(VMErrorKind::InvalidCharacter(n));
+                };
 
-                    let mut input = String::new();
+                self.write_output(format_args!("{}", c))?;
+                self.drop_stack(1);
+            },
 
-                    if let Err(_) = io::stdin().read_line(&mut input) {
-                        return Err(VMError::new("Could not read from user input"));
+            Instruction::OutI => {
+                let n = self.get_stack(0)?;
+
+                self.write_output(format_args!("{}", n))?;
+                self.drop_stack(1);
+            },
+
+            Instruction::ReadC => {
+                let loc = self.get_stack(0)?;
+
+                if loc.is_negative() {
+                    return Err(VMErrorKind::NegativeHeapAddress(loc));
+                };
+
+                self.flush_output()?;
+
+                match self.read_byte() {
+                    Ok(Some(byte)) => {
+                        self.store_heap(loc, Value::from(byte))?;
+
+                        if self.dialect.is_strict() {
+                            self.pop_stack(0)?;
+                        };
+                    },
+
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(Status::WaitingForInput),
+
+                    _ => return Err(VMErrorKind::InputError),
+                };
+            },
+
+            Instruction::ReadI => {
+                let loc = self.get_stack(0)?;
+
+                if loc.is_negative() {
+                    return Err(VMErrorKind::NegativeHeapAddress(loc));
+                };
+
+                self.flush_output()?;
+
+                loop {
+                    match self.read_byte() {
+                        Ok(Some(b'\n')) | Ok(None) => break,
+                        Ok(Some(byte)) => self.input_line.push(byte),
+
+                        Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(Status::WaitingForInput),
+
+                        Err(_) => return Err(VMErrorKind::InputError),
                     };
+                }
 
-                    if let Ok(num) = input.trim().parse::<i32>() {
-                        self.heap.insert(loc as u32, num);
+                let input = String::from_utf8_lossy(&self.input_line).into_owned();
 
-                    } else {
-                        return Err(VMError::new(&format!("Could not read number {}", input)));
+                self.input_line.clear();
+
+                if let Some(num) = input.trim().parse::<Value>().ok().filter(|num| self.numeric_mode.contains(num)) {
+                    self.store_heap(loc, num)?;
+
+                    if self.dialect.is_strict() {
+                        self.pop_stack(0)?;
                     };
-                },
 
-                _ => {
-                    return Err(VMError::new("Invalid command"));
-                },
-            };
+                } else {
+                    return Err(VMErrorKind::InvalidNumber(input));
+                };
+            },
 
-            self.pointer += 1;
-        }
 
-        return Ok(());
+            Instruction::Extension(ref opcode) => {
+                let Some(extension) = self.extensions.get(opcode) else {
+                    return Err(VMErrorKind::UnknownExtension(opcode.clone()));
+                };
+
+                let mut context = ExtensionContext::new(&mut self.stack, &mut self.heap, self.limits, self.numeric_mode, &mut self.output);
+
+                if let Err(message) = extension.call(&mut context) {
+                    // Failures of the machine's own checks keep their kind
+                    return Err(context.into_error().unwrap_or(VMErrorKind::ExtensionFailed { name: extension.name.clone(), message }));
+                };
+            },
+
+        };
+
+        self.pointer += 1;
+
+        Ok(Status::Running)
     }
 
-    fn get_stack(&self, index: usize) -> Result<i32, VMError> {
-        if self.stack.is_empty() {
-            return Err(VMError::new("Stack is empty"));
-        }
+    pub fn program(&self) -> &Arc<Program> {
+        &self.program
+    }
 
-        let index = (self.stack.len() as isize) - 1 - index as isize;
+    pub fn input(&self) -> &I {
+        &self.input
+    }
+
+    pub fn output(&self) -> &O {
+        &self.output
+    }
+
+    pub fn into_io(self) -> (I, O) {
+        (self.input, self.output)
+    }
+
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    pub fn pointer(&self) -> usize {
+        self.pointer
+    }
 
-        if index < 0 {
-            return Err(VMError::new("Stack index is negative"));
+    pub fn stack(&self) -> &[Value] {
+        &self.stack
+    }
+
+    pub fn heap(&self) -> &HashMap<Value, Value> {
+        &self.heap
+    }
+
+    pub fn subroutine_stack(&self) -> &[usize] {
+        &self.subroutine_stack
+    }
+
+    /// Attaches the machine's current position and state to an error.
+    fn error(&self, kind: VMErrorKind) -> VMError {
+        let stack_top = self.stack[self.stack.len().saturating_sub(STACK_SNAPSHOT)..].to_vec();
+
+        VMError::new(kind, self.pointer, self.program.location(self.pointer).copied(), stack_top, self.subroutine_stack.clone())
+    }
+
+    fn get_stack(&self, index: usize) -> Result<Value, VMErrorKind> {
+        if index >= self.stack.len() {
+            return Err(VMErrorKind::StackUnderflow);
         }
 
-        let res = self.stack.get(index as usize);
+        Ok(self.stack[self.stack.len() - 1 - index].clone())
+    }
 
-        if let None = res {
-            return Err(VMError::new("Stack index out of bounds"));
+    /// Pushes a new number onto the stack, after bringing it into the range
+    /// of the numeric mode.
+    fn push_number(&mut self, n: Value) -> Result<(), VMErrorKind> {
+        match self.numeric_mode.normalize(n) {
+            Ok(n) => self.push_stack(n),
+            Err(value) => Err(VMErrorKind::Overflow { value, mode: self.numeric_mode }),
         }
+    }
+
+    /// Replaces the top two values with the result of `f`, which is given
+    /// them deepest first. The stack is only changed once the result is known
+    /// to fit, so that a failing instruction leaves its operands in place.
+    fn arithmetic(&mut self, f: impl FnOnce(Value, Value) -> Result<Value, VMErrorKind>) -> Result<(), VMErrorKind> {
+        let n1 = self.get_stack(0)?;
+        let n2 = self.get_stack(1)?;
+
+        let n = match self.numeric_mode.normalize(f(n2, n1)?) {
+            Ok(n) => n,
+            Err(value) => return Err(VMErrorKind::Overflow { value, mode: self.numeric_mode }),
+        };
+
+        self.drop_stack(2);
+        self.push_stack(n)
+    }
+
+    fn push_stack(&mut self, n: Value) -> Result<(), VMErrorKind> {
+        self.limits.check_stack(self.stack.len())?;
+
+        self.stack.push(n);
 
-        Ok(*res.unwrap())
+        Ok(())
     }
 
-    fn pop_stack(&mut self, index: usize) -> Result<i32, VMError> {
+    fn store_heap(&mut self, index: Value, n: Value) -> Result<(), VMErrorKind> {
+        self.limits.check_heap(&self.heap, &index)?;
+
+        self.heap.insert(index, n);
+
+        Ok(())
+    }
+
+    fn pop_stack(&mut self, index: usize) -> Result<Value, VMErrorKind> {
         let res = self.get_stack(index)?;
 
         self.stack.pop();
 
-        return Ok(res);
+        Ok(res)
     }
 
-    fn get_label(&self, label: &str) -> Result<usize, VMError> {
-        let label_addr = self.labels.get(label);
+    /// Removes the top `count` values, which must be on the stack.
+    fn drop_stack(&mut self, count: usize) {
+        self.stack.truncate(self.stack.len() - count);
+    }
 
-        if let None = label_addr {
-            return Err(VMError::new(&format!("Label {} does not exist", label)));
-        }
+    fn write_output(&mut self, args: std::fmt::Arguments) -> Result<(), VMErrorKind> {
+        if self.output.write_fmt(args).is_err() {
+            return Err(VMErrorKind::OutputError);
+        };
+
+        Ok(())
+    }
 
-        Ok(*label_addr.unwrap())
+    fn flush_output(&mut self) -> Result<(), VMErrorKind> {
+        if self.output.flush().is_err() {
+            return Err(VMErrorKind::OutputError);
+        };
+
+        Ok(())
+    }
+
+    /// Reads a single byte, or `None` at the end of the input.
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte: [u8; 1] = [0];
+
+        loop {
+            match self.input.read(&mut byte) {
+                Ok(0) => return Ok(None),
+                Ok(_) => return Ok(Some(byte[0])),
+
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => return Err(e),
+            };
+        }
     }
 }
\ No newline at end of file
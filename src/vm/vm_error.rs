@@ -1,26 +1,126 @@
+use super::limits::Limit;
+use super::number::{NumericMode, Value};
+use super::parser::token::{Location, Opcode};
+
 use std::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VMErrorKind {
+    StackUnderflow,
+    InvalidStackIndex(Value),
+    NegativeHeapAddress(Value),
+    DivisionByZero,
+    Overflow {
+        value: Value,
+        mode: NumericMode,
+    },
+    InvalidCharacter(Value),
+    InputError,
+    InvalidNumber(String),
+    OutputError,
+    ReturnOutsideSubroutine,
+    MissingEnd,
+    UnknownExtension(Opcode),
+    ExtensionFailed {
+        name: String,
+        message: String,
+    },
+    LimitExceeded {
+        limit: Limit,
+        max: usize,
+    },
+}
+
+impl fmt::Display for VMErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VMErrorKind::StackUnderflow => write!(f, "Stack does not hold enough values"),
+            VMErrorKind::InvalidStackIndex(n) => write!(f, "Stack index {} is out of bounds", n),
+            VMErrorKind::NegativeHeapAddress(n) => write!(f, "Heap index {} can not be negative", n),
+            VMErrorKind::DivisionByZero => write!(f, "Division by zero"),
+            VMErrorKind::Overflow { value, mode } => write!(f, "{} does not fit in a {} integer", value, mode),
+            VMErrorKind::InvalidCharacter(n) => write!(f, "{} is not a valid character", n),
+            VMErrorKind::InputError => write!(f, "Could not read from user input"),
+            VMErrorKind::InvalidNumber(input) => write!(f, "Could not read number {:?}", input),
+            VMErrorKind::OutputError => write!(f, "Could not write to output"),
+            VMErrorKind::ReturnOutsideSubroutine => write!(f, "Returned without a subroutine call"),
+            VMErrorKind::MissingEnd => write!(f, "Program ran past its last instruction without ending"),
+            VMErrorKind::UnknownExtension(opcode) => write!(f, "No extension is registered for {}", opcode),
+            VMErrorKind::ExtensionFailed { name, message } => write!(f, "Extension {} failed: {}", name, message),
+            VMErrorKind::LimitExceeded { limit, max } => write!(f, "Exceeded the {} limit of {}", limit, max),
+        }
+    }
+}
+
+/// An error raised while running a program, along with where it happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct VMError {
-    message: String,
+    // Boxed so that results carrying an error stay small on the happy path.
+    details: Box<Details>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Details {
+    kind: VMErrorKind,
+
+    pointer: usize,
+    location: Option<Location>,
+
+    stack_top: Vec<Value>,
+    call_stack: Vec<usize>,
 }
 
 impl VMError {
-    pub fn new(message: &str) -> Self {
+    pub fn new(kind: VMErrorKind, pointer: usize, location: Option<Location>, stack_top: Vec<Value>, call_stack: Vec<usize>) -> Self {
         Self {
-            message: message.to_string(),
+            details: Box::new(Details {
+                kind,
+
+                pointer,
+                location,
+
+                stack_top,
+                call_stack,
+            }),
         }
     }
+
+    pub fn kind(&self) -> &VMErrorKind {
+        &self.details.kind
+    }
+
+    /// The index of the instruction that failed.
+    pub fn pointer(&self) -> usize {
+        self.details.pointer
+    }
+
+    /// Where the failing instruction is in the source, if it is in the program.
+    pub fn location(&self) -> Option<&Location> {
+        self.details.location.as_ref()
+    }
+
+    /// The topmost values on the stack when the error was raised, with the top
+    /// of the stack last.
+    pub fn stack_top(&self) -> &[Value] {
+        &self.details.stack_top
+    }
+
+    /// The instruction indices of the active subroutine calls, innermost last.
+    pub fn call_stack(&self) -> &[usize] {
+        &self.details.call_stack
+    }
 }
 
 impl fmt::Display for VMError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.message)
+        write!(f, "{} at instruction {}", self.details.kind, self.details.pointer)?;
+
+        if let Some(location) = &self.details.location {
+            write!(f, " on line {}, column {}", location.line, location.column)?;
+        };
+
+        Ok(())
     }
 }
 
-impl std::error::Error for VMError {
-    fn description(&self) -> &str {
-        &self.message
-    }
-}
\ No newline at end of file
+impl std::error::Error for VMError {}
\ No newline at end of file
@@ -0,0 +1,113 @@
+mod common;
+
+use common::{commands, program, source_in};
+use whitespace::{Alphabet, CommandType, Location, Parser, ParserErrorKind, WhitespacePolicy};
+
+#[test]
+fn zero_width_programs_hide_in_text() {
+    let alphabet = Alphabet::zero_width();
+
+    let hidden = source_in(&alphabet, &program());
+    let (first, second) = hidden.split_at(hidden.char_indices().nth(4).unwrap().0);
+
+    let text = format!("Nothing{} to see\there.\n{}", first, second);
+
+    let output = Parser::new(text).with_alphabet(alphabet).parse_all();
+
+    assert!(output.errors.is_empty());
+    assert_eq!(commands(&output.tokens), program());
+}
+
+#[test]
+fn custom_alphabets_replace_space_tab_and_linefeed() {
+    let alphabet = Alphabet::new('a', 'b', 'c');
+
+    let output = Parser::new(format!("xyz {}\t\n", source_in(&alphabet, &program()))).with_alphabet(alphabet).parse_all();
+
+    assert!(output.errors.is_empty());
+    assert_eq!(commands(&output.tokens), program());
+    assert_eq!(output.tokens[0].location, Location { start: 4, end: 15, line: 1, column: 5 });
+
+    assert_eq!(alphabet.symbol('b'), Some('\t'));
+    assert_eq!(alphabet.symbol(' '), None);
+    assert!(alphabet.is_other_whitespace(' '));
+}
+
+#[test]
+#[should_panic(expected = "alphabet characters must be distinct")]
+fn alphabet_characters_must_differ() {
+    Alphabet::new('a', 'b', 'a');
+}
+
+/// Two `Add`s, each after a vertical tab, which is whitespace outside the
+/// standard alphabet.
+const VERTICAL_TABS: &str = "\u{0B}\t   \u{0B}\t   ";
+
+#[test]
+fn other_whitespace_is_a_comment_by_default() {
+    let output = Parser::new(String::from(VERTICAL_TABS)).parse_all();
+
+    assert!(output.errors.is_empty());
+    assert_eq!(commands(&output.tokens), [CommandType::Add, CommandType::Add]);
+
+    let columns: Vec<usize> = output.tokens.iter().map(|token| token.location.column).collect();
+
+    assert_eq!(columns, [2, 7]);
+}
+
+#[test]
+fn ignored_whitespace_does_not_count_towards_columns() {
+    let alphabet = Alphabet::standard().with_other_whitespace(WhitespacePolicy::Ignore);
+
+    let output = Parser::new(String::from(VERTICAL_TABS)).with_alphabet(alphabet).parse_all();
+
+    assert!(output.errors.is_empty());
+
+    let positions: Vec<(usize, usize)> = output.tokens.iter().map(|token| (token.location.start, token.location.column)).collect();
+
+    assert_eq!(positions, [(1, 1), (6, 5)]);
+
+    // Linefeeds outside the alphabet are ignored too, but still start a new line
+    let alphabet = Alphabet::new('S', 'T', 'L').with_other_whitespace(WhitespacePolicy::Ignore);
+
+    let output = Parser::new(String::from("\u{0B}TSSS\r\n\u{0B}TSSS")).with_alphabet(alphabet).parse_all();
+
+    assert!(output.errors.is_empty());
+
+    let positions: Vec<(usize, usize, usize)> = output.tokens.iter().map(|token| (token.location.start, token.location.line, token.location.column)).collect();
+
+    assert_eq!(positions, [(1, 1, 1), (8, 2, 1)]);
+}
+
+#[test]
+fn other_whitespace_can_be_an_error() {
+    let alphabet = Alphabet::standard().with_other_whitespace(WhitespacePolicy::Error);
+
+    let output = Parser::new(String::from(VERTICAL_TABS)).with_alphabet(alphabet).parse_all();
+
+    assert_eq!(commands(&output.tokens), [CommandType::Add, CommandType::Add]);
+
+    let errors: Vec<(&ParserErrorKind, usize)> = output.errors.iter().map(|error| (error.kind(), error.span().column)).collect();
+
+    assert_eq!(errors, [
+        (&ParserErrorKind::UnexpectedWhitespace { character: '\u{0B}' }, 1),
+        (&ParserErrorKind::UnexpectedWhitespace { character: '\u{0B}' }, 6),
+    ]);
+
+    assert!(Parser::new(String::from(VERTICAL_TABS)).with_alphabet(alphabet).parse().is_err());
+
+    let lexemes: Vec<bool> = Parser::new(String::from(VERTICAL_TABS)).with_alphabet(alphabet).lexemes().map(|lexeme| lexeme.is_ok()).collect();
+
+    assert_eq!(lexemes, [false, true, true, false, true, true]);
+}
+
+#[test]
+fn ordinary_whitespace_is_outside_the_zero_width_alphabet() {
+    let alphabet = Alphabet::zero_width().with_other_whitespace(WhitespacePolicy::Error);
+
+    let output = Parser::new(format!("a b{}", source_in(&alphabet, &program()))).with_alphabet(alphabet).parse_all();
+
+    assert_eq!(commands(&output.tokens), program());
+    assert_eq!(output.errors.len(), 1);
+    assert_eq!(output.errors[0].kind(), &ParserErrorKind::UnexpectedWhitespace { character: ' ' });
+}
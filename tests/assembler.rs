@@ -0,0 +1,138 @@
+use whitespace::{assemble, AsmErrorKind, Assembler, CommandType, Extensions, ImpType, NumericMode, Opcode, Parser};
+
+/// Every command, each after a label so that every jump has somewhere to go.
+const NATIVE: &str = "
+    label start     ; defines label 1
+    push -5
+    dup
+    copy 2
+    swap
+    drop
+    disc
+    slide 3
+
+    add
+    sub
+    mul
+    div
+    mod
+    store
+    retr
+
+    call start
+    jump end
+    jz start
+    jn end
+    ret
+    end
+
+    outc
+    outi
+    readc
+    readi
+
+    debug_print_stack
+    debug_print_heap
+label end
+";
+
+#[test]
+fn every_mnemonic_is_assembled_into_source_the_parser_accepts() {
+    let extensions = Extensions::debug();
+
+    let commands = Assembler::new().with_extensions(&extensions).assemble(NATIVE).unwrap();
+
+    assert_eq!(commands, [
+        CommandType::Labl(String::from("\t")),
+        CommandType::Push((-5).into()),
+        CommandType::Dup,
+        CommandType::Copy(2.into()),
+        CommandType::Swap,
+        CommandType::Disc,
+        CommandType::Disc,
+        CommandType::Slide(3.into()),
+
+        CommandType::Add,
+        CommandType::Sub,
+        CommandType::Mult,
+        CommandType::IDiv,
+        CommandType::Mod,
+        CommandType::Store,
+        CommandType::Retr,
+
+        CommandType::Call(String::from("\t")),
+        CommandType::Jump(String::from("\t ")),
+        CommandType::JumpZ(String::from("\t")),
+        CommandType::JumpN(String::from("\t ")),
+        CommandType::EndS,
+        CommandType::EndP,
+
+        CommandType::OutC,
+        CommandType::OutI,
+        CommandType::ReadC,
+        CommandType::ReadI,
+
+        CommandType::Extension(Opcode::new(ImpType::IO, "\n ")),
+        CommandType::Extension(Opcode::new(ImpType::IO, "\n\t")),
+        CommandType::Labl(String::from("\t ")),
+    ]);
+
+    let source = Assembler::new().with_extensions(&extensions).assemble_to_whitespace(NATIVE).unwrap();
+
+    let program = Parser::new(source).with_extensions(&extensions).parse().unwrap();
+
+    assert_eq!(program.commands().collect::<Vec<_>>(), commands);
+}
+
+#[test]
+fn mnemonics_ignore_case_and_comments() {
+    assert_eq!(assemble("PUSH 1 # one\n  OutI ; print it\n\n# nothing\nEND"), assemble("push 1\nouti\nend"));
+}
+
+#[test]
+fn raw_labels_keep_their_spaces_and_tabs() {
+    let commands = Assembler::new().assemble("label a\nlabel %\nlabel %TS\nlabel b\njump %TS").unwrap();
+
+    // Named labels skip the numbers that are written out
+    assert_eq!(commands, [
+        CommandType::Labl(String::from("\t")),
+        CommandType::Labl(String::from("")),
+        CommandType::Labl(String::from("\t ")),
+        CommandType::Labl(String::from("\t\t")),
+        CommandType::Jump(String::from("\t ")),
+    ]);
+}
+
+#[test]
+fn labels_must_be_defined_once() {
+    let error = Assembler::new().assemble("label a\npush 1\nlabel a").unwrap_err();
+
+    assert_eq!(error.kind(), &AsmErrorKind::DuplicateLabel { label: String::from("a"), previous: 1 });
+    assert_eq!(error.line(), 3);
+    assert_eq!(error.to_string(), "Label a is defined again on line 3 (first defined on line 1)");
+
+    let error = Assembler::new().assemble("label a\njump a\n\ncall b").unwrap_err();
+
+    assert_eq!(error.kind(), &AsmErrorKind::UndefinedLabel(String::from("b")));
+    assert_eq!(error.line(), 4);
+}
+
+#[test]
+fn arguments_are_checked() {
+    let kind = |source: &str| Assembler::new().assemble(source).unwrap_err().kind().clone();
+
+    assert_eq!(kind("pusj 1"), AsmErrorKind::UnknownMnemonic(String::from("pusj")));
+    assert_eq!(kind("push"), AsmErrorKind::MissingArgument { mnemonic: String::from("push") });
+    assert_eq!(kind("dup 1"), AsmErrorKind::UnexpectedArgument { mnemonic: String::from("dup") });
+    assert_eq!(kind("copy 1 2"), AsmErrorKind::UnexpectedArgument { mnemonic: String::from("copy") });
+    assert_eq!(kind("push one"), AsmErrorKind::InvalidNumber(String::from("one")));
+    assert_eq!(kind("jump %SX"), AsmErrorKind::InvalidLabel(String::from("%SX")));
+}
+
+#[test]
+fn big_numbers_are_assembled_for_big_integer_programs() {
+    let source = assemble("push 123456789012345678901234567890\nouti\nend").unwrap();
+
+    assert!(Parser::new(source.clone()).parse().is_err());
+    assert!(Parser::new(source).with_numeric_mode(NumericMode::BigInt).parse().is_ok());
+}
@@ -0,0 +1,32 @@
+//! Helpers shared by the integration tests.
+
+#![allow(dead_code)]
+
+use whitespace::{encode, Alphabet, CommandType, Token, TokenType};
+
+/// Writes `commands` out as Whitespace source.
+pub fn source(commands: &[CommandType]) -> String {
+    commands.iter().map(encode).collect()
+}
+
+/// Writes `commands` out in the characters of `alphabet`.
+pub fn source_in(alphabet: &Alphabet, commands: &[CommandType]) -> String {
+    source(commands).chars().map(|c| match c {
+        ' ' => alphabet.space(),
+        '\t' => alphabet.tab(),
+        _ => alphabet.linefeed(),
+    }).collect()
+}
+
+/// The commands of `tokens`, which must all be whole commands.
+pub fn commands(tokens: &[Token]) -> Vec<CommandType> {
+    tokens.iter().map(|token| match &token.token_type {
+        TokenType::Command(command) => command.clone(),
+        TokenType::Imp(imp) => panic!("unexpected {:?} IMP", imp),
+    }).collect()
+}
+
+/// A program that prints `H`.
+pub fn program() -> Vec<CommandType> {
+    vec![CommandType::Push(72.into()), CommandType::OutC, CommandType::EndP]
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use common::source;
+use whitespace::{disassemble, Assembler, CommandType, Disassembler, Extensions, ImpType, Opcode, Parser, Program, Syntax};
+
+/// A program whose labels are not all numbered the way the assembler would
+/// number them, and which uses an extension.
+fn commands() -> Vec<CommandType> {
+    vec![
+        CommandType::Jump(String::from("\t ")),
+        CommandType::Labl(String::from("\t")),
+        CommandType::Push((-12).into()),
+        CommandType::Copy(0.into()),
+        CommandType::Slide(2.into()),
+        CommandType::JumpZ(String::from("")),
+        CommandType::Labl(String::from("\t ")),
+        CommandType::Call(String::from("  \t")),
+        CommandType::Labl(String::from("")),
+        CommandType::Labl(String::from("\t\t")),
+        CommandType::Extension(Opcode::new(ImpType::IO, "\n ")),
+        CommandType::JumpN(String::from("\t")),
+        CommandType::Labl(String::from("  \t")),
+        CommandType::Retr,
+        CommandType::EndS,
+        CommandType::EndP,
+    ]
+}
+
+fn program() -> Program {
+    Parser::new(source(&commands())).with_extensions(&Extensions::debug()).parse().unwrap()
+}
+
+#[test]
+fn disassembly_reassembles_into_the_same_source() {
+    for syntax in [Syntax::Native, Syntax::Wsa, Syntax::Reference] {
+        for extensions in [Extensions::new(), Extensions::debug()] {
+            let disassembly = Disassembler::new().with_syntax(syntax).with_extensions(&extensions).disassemble(&program());
+
+            let assembled = Assembler::new().with_syntax(syntax).with_extensions(&extensions).assemble(&disassembly)
+                .unwrap_or_else(|error| panic!("{:?} disassembly did not assemble: {}\n{}", syntax, error, disassembly));
+
+            assert_eq!(assembled, commands(), "{:?} disassembly:\n{}", syntax, disassembly);
+        }
+    }
+}
+
+#[test]
+fn disassembly_shows_indices_lines_and_both_forms_of_labels() {
+    let program = Parser::new(source(&[
+        CommandType::Labl(String::from("\t")),
+        CommandType::Push(5.into()),
+        CommandType::JumpZ(String::from(" ")),
+        CommandType::Labl(String::from(" ")),
+        CommandType::Extension(Opcode::new(ImpType::IO, "\n ")),
+        CommandType::Jump(String::from("\t")),
+    ])).with_extensions(&Extensions::debug()).parse().unwrap();
+
+    assert_eq!(disassemble(&program), "\
+label label_0               ; 0 (line 1) %T
+    push 5                  ; 1 (line 3)
+    jz %S                   ; 2 (line 4) label_1
+label %S                    ; 3 (line 6) label_1
+    %TLLS                   ; 4 (line 8)
+    jump label_0            ; 5 (line 10) %T
+");
+
+    assert_eq!(Disassembler::new().with_syntax(Syntax::Reference).with_extensions(&Extensions::debug()).disassemble(&program), "\
+label T                     ; 0 (line 1) label_0
+    push 5                  ; 1 (line 3)
+    ifzero S                ; 2 (line 4) label_1
+label S                     ; 3 (line 6) label_1
+    debug_print_stack       ; 4 (line 8)
+    jump T                  ; 5 (line 10) label_0
+");
+}
+
+#[test]
+fn raw_extensions_must_not_overlap_standard_commands() {
+    assert!(Assembler::new().assemble("%TLLS").is_ok());
+    assert!(Assembler::new().assemble("%SLS").is_err());
+    assert!(Assembler::new().assemble("%TL").is_err());
+    assert!(Assembler::new().assemble("%TLLX").is_err());
+}
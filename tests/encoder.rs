@@ -0,0 +1,224 @@
+//! Checks that the encoder is the inverse of the parser, on randomly generated
+//! commands and sources.
+
+mod common;
+
+use common::commands;
+use whitespace::{encode, encode_instruction, encode_program, CommandType, Extensions, ImpType, NumericMode, Opcode, Parser, ProgramBuilder, Token, TokenType, Value, VirtualMachine};
+
+const CASES: usize = 500;
+
+/// A xorshift generator, so that failures can be reproduced from the seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn number(&mut self) -> Value {
+        let n = match self.below(4) {
+            0 => Value::from(self.below(3)),
+            1 => Value::from(self.next() >> self.below(64)),
+            _ => (0..self.below(4) + 1).fold(Value::from(0), |n, _| (n << 64) + self.next()),
+        };
+
+        if self.below(2) == 0 {-n} else {n}
+    }
+
+    fn label(&mut self) -> String {
+        (0..self.below(10)).map(|_| if self.below(2) == 0 {' '} else {'\t'}).collect()
+    }
+
+    fn command(&mut self, labels: &[String]) -> CommandType {
+        let label = |rng: &mut Self| match labels.is_empty() {
+            true => rng.label(),
+            false => labels[rng.below(labels.len() as u64) as usize].clone(),
+        };
+
+        match self.below(26) {
+            0 => CommandType::Push(self.number()),
+            1 => CommandType::Dup,
+            2 => CommandType::Copy(self.number()),
+            3 => CommandType::Swap,
+            4 => CommandType::Disc,
+            5 => CommandType::Slide(self.number()),
+
+            6 => CommandType::Add,
+            7 => CommandType::Sub,
+            8 => CommandType::Mult,
+            9 => CommandType::IDiv,
+            10 => CommandType::Mod,
+            11 => CommandType::Store,
+            12 => CommandType::Retr,
+
+            13 => CommandType::Labl(label(self)),
+            14 => CommandType::Call(label(self)),
+            15 => CommandType::Jump(label(self)),
+            16 => CommandType::JumpZ(label(self)),
+            17 => CommandType::JumpN(label(self)),
+            18 => CommandType::EndS,
+            19 => CommandType::EndP,
+
+            20 => CommandType::OutC,
+            21 => CommandType::OutI,
+            22 => CommandType::ReadC,
+            23 => CommandType::ReadI,
+
+            24 => CommandType::Extension(Opcode::new(ImpType::IO, "\n ")),
+            _ => CommandType::Extension(Opcode::new(ImpType::IO, "\n\t")),
+        }
+    }
+
+    fn source(&mut self) -> String {
+        (0..self.below(60)).map(|_| [' ', '\t', '\n'][self.below(3) as usize]).collect()
+    }
+}
+
+fn parser(source: String) -> Parser {
+    Parser::new(source)
+        .with_numeric_mode(NumericMode::BigInt)
+        .with_extensions(&Extensions::debug())
+}
+
+/// Whether a number is written without leading zeros and is not negative
+/// zero, which are the only forms the encoder does not reproduce.
+fn is_canonical_number(number: &str) -> bool {
+    let digits = &number[1..number.len() - 1];
+
+    let leading_zero = digits.starts_with(' ');
+    let negative_zero = number.starts_with('\t') && digits.is_empty();
+
+    !leading_zero && !negative_zero
+}
+
+#[test]
+fn parsing_an_encoded_command_gives_it_back() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+
+    for _ in 0..CASES {
+        let expected: Vec<CommandType> = (0..rng.below(20)).map(|_| rng.command(&[])).collect();
+        let source: String = expected.iter().map(encode).collect();
+
+        let tokens: Vec<Token> = parser(source.clone()).collect::<Result<_, _>>()
+            .unwrap_or_else(|error| panic!("{:?} did not parse: {}", source, error));
+
+        assert_eq!(commands(&tokens), expected, "source {:?}", source);
+
+        for (token, command) in tokens.iter().zip(&expected) {
+            assert_eq!(token.location.end - token.location.start, encode(command).len());
+        }
+    }
+}
+
+#[test]
+fn encoding_a_parsed_source_gives_it_back_in_canonical_form() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+
+    for _ in 0..CASES * 10 {
+        let source = rng.source();
+
+        let Ok(tokens) = parser(source.clone()).collect::<Result<Vec<_>, _>>() else {
+            continue;
+        };
+
+        let encoded: String = commands(&tokens).iter().map(encode).collect();
+
+        let canonical = tokens.iter().all(|token| {
+            let text = &source[token.location.start..token.location.end];
+
+            match &token.token_type {
+                TokenType::Command(CommandType::Push(_)) => is_canonical_number(&text[2..]),
+                TokenType::Command(CommandType::Copy(_) | CommandType::Slide(_)) => is_canonical_number(&text[3..]),
+                _ => true,
+            }
+        });
+
+        if canonical {
+            assert_eq!(encoded, source);
+        };
+
+        let reparsed: Vec<Token> = parser(encoded.clone()).collect::<Result<_, _>>().unwrap();
+
+        assert_eq!(commands(&reparsed), commands(&tokens), "source {:?}", source);
+        assert!(encoded.len() <= source.len());
+    }
+}
+
+#[test]
+fn encoding_a_linked_program_gives_its_source_back() {
+    let mut rng = Rng(0xdead_beef_cafe_f00d);
+
+    for _ in 0..CASES {
+        let labels: Vec<String> = (0..rng.below(5) + 1).map(|_| rng.label()).collect::<std::collections::BTreeSet<_>>().into_iter().collect();
+
+        let mut commands: Vec<CommandType> = labels.iter().map(|label| CommandType::Labl(label.clone())).collect();
+
+        for _ in 0..rng.below(30) {
+            let command = match rng.command(&labels) {
+                CommandType::Labl(_) => CommandType::Dup,
+                command => command,
+            };
+
+            let index = rng.below(commands.len() as u64 + 1) as usize;
+
+            commands.insert(index, command);
+        }
+
+        let source: String = commands.iter().map(encode).collect();
+        let program = parser(source.clone()).parse().unwrap();
+
+        assert_eq!(encode_program(&program), source);
+        assert_eq!(program.commands().collect::<Vec<_>>(), commands);
+
+        for (index, command) in commands.iter().enumerate() {
+            assert_eq!(encode_instruction(&program, index), Some(encode(command)));
+        }
+
+        assert_eq!(encode_instruction(&program, commands.len()), None);
+    }
+}
+
+#[test]
+fn built_programs_match_their_parsed_source() {
+    let mut builder = ProgramBuilder::new();
+
+    builder.push(3)
+        .label("loop").dup().jump_if_zero("end")
+        .dup().out_int().push(-1).add().call("newline").jump("loop")
+        .label("newline").push('\n' as u32).out_char().ret()
+        .label("end").end();
+
+    let program = builder.build().unwrap();
+
+    assert_eq!(program, parser(builder.to_whitespace()).parse().unwrap());
+    assert_eq!(builder.label_of("loop"), Some("\t"));
+    assert_eq!(builder.label_of("end"), Some("\t "));
+
+    let mut virtual_machine = VirtualMachine::with_io(program, &b""[..], Vec::new());
+
+    virtual_machine.run().unwrap();
+
+    assert_eq!(virtual_machine.output(), b"3\n2\n1\n");
+}
+
+#[test]
+fn building_checks_labels() {
+    let mut builder = ProgramBuilder::new();
+
+    builder.label("a").jump("b");
+
+    assert!(builder.build().is_err());
+
+    builder.label("b").label("a");
+
+    assert!(builder.build().is_err());
+}
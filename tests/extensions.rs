@@ -0,0 +1,117 @@
+use whitespace::{ExtensionContext, Extensions, ImpType, Limit, Limits, NumericMode, Opcode, ProgramBuilder, Status, Value, VirtualMachine, VMErrorKind};
+
+fn run(extensions: Extensions, f: impl FnOnce(&mut ProgramBuilder) -> &mut ProgramBuilder) -> VirtualMachine<&'static [u8], Vec<u8>> {
+    let mut builder = ProgramBuilder::new();
+
+    f(&mut builder);
+
+    VirtualMachine::with_io(builder.build().unwrap(), &b""[..], Vec::new()).with_extensions(extensions)
+}
+
+/// A registry with a single extension at the free arithmetic pattern `TL`.
+fn extension(callback: impl Fn(&mut ExtensionContext) -> Result<(), String> + Send + Sync + 'static) -> Extensions {
+    let mut extensions = Extensions::new();
+
+    extensions.register("test", Opcode::new(ImpType::Arithmetic, "\t\n"), callback);
+
+    extensions
+}
+
+#[test]
+fn debug_extensions_print_the_stack_and_heap() {
+    let mut virtual_machine = run(Extensions::debug(), |b| b
+        .push(5).push(2).push(-7).store().push(1).push(8).store().push(3)
+        .extension(Opcode::new(ImpType::IO, "\n "))
+        .extension(Opcode::new(ImpType::IO, "\n\t"))
+        .end()
+    );
+
+    assert_eq!(virtual_machine.run(), Ok(Status::Halted));
+    assert_eq!(virtual_machine.output(), b"[5, 3]\n{1: 8, 2: -7}\n");
+}
+
+#[test]
+fn unregistered_extensions_fail_when_run() {
+    let mut virtual_machine = run(Extensions::new(), |b| b.extension(Opcode::new(ImpType::IO, "\n ")));
+
+    assert_eq!(virtual_machine.run().unwrap_err().kind(), &VMErrorKind::UnknownExtension(Opcode::new(ImpType::IO, "\n ")));
+}
+
+#[test]
+fn extensions_change_the_stack_and_heap() {
+    let extensions = extension(|context| {
+        let n = context.pop()?;
+        let doubled = context.retrieve(&n) * 2;
+
+        context.store(n, doubled)?;
+        context.push(Value::from(context.stack().len()))
+    });
+
+    let mut virtual_machine = run(extensions, |b| b.push(9).push(4).push(21).store().push(4).extension(Opcode::new(ImpType::Arithmetic, "\t\n")).end());
+
+    assert_eq!(virtual_machine.run(), Ok(Status::Halted));
+    assert_eq!(virtual_machine.stack(), [Value::from(9), Value::from(1)]);
+    assert_eq!(virtual_machine.heap()[&Value::from(4)], Value::from(42));
+}
+
+#[test]
+fn extensions_are_held_to_the_limits() {
+    let extensions = extension(|context| context.push(Value::from(1)));
+
+    let mut virtual_machine = run(extensions, |b| b.push(1).extension(Opcode::new(ImpType::Arithmetic, "\t\n")));
+
+    virtual_machine.set_limits(Limits { max_stack: Some(1), ..Limits::default() });
+
+    assert_eq!(virtual_machine.run().unwrap_err().kind(), &VMErrorKind::LimitExceeded { limit: Limit::Stack, max: 1 });
+
+    let extensions = extension(|context| context.store(Value::from(context.heap().len()), Value::from(1)));
+
+    let mut virtual_machine = run(extensions, |b| b.extension(Opcode::new(ImpType::Arithmetic, "\t\n")).extension(Opcode::new(ImpType::Arithmetic, "\t\n")));
+
+    virtual_machine.set_limits(Limits { max_heap: Some(1), ..Limits::default() });
+
+    assert_eq!(virtual_machine.run().unwrap_err().kind(), &VMErrorKind::LimitExceeded { limit: Limit::Heap, max: 1 });
+}
+
+#[test]
+fn extensions_are_held_to_the_numeric_mode() {
+    let extensions = extension(|context| context.push(Value::from(i64::MAX)));
+
+    let error = run(extensions.clone(), |b| b.extension(Opcode::new(ImpType::Arithmetic, "\t\n"))).run().unwrap_err();
+
+    assert_eq!(error.kind(), &VMErrorKind::Overflow { value: Value::from(i64::MAX), mode: NumericMode::Checked });
+
+    let mut virtual_machine = run(extensions, |b| b.extension(Opcode::new(ImpType::Arithmetic, "\t\n"))).with_numeric_mode(NumericMode::Wrapping);
+
+    assert_eq!(virtual_machine.run(), Ok(Status::Halted));
+    assert_eq!(virtual_machine.stack(), [Value::from(-1)]);
+}
+
+#[test]
+fn extension_errors_stop_the_program() {
+    let error = run(extension(|_| Err(String::from("no"))), |b| b.extension(Opcode::new(ImpType::Arithmetic, "\t\n"))).run().unwrap_err();
+
+    assert_eq!(error.kind(), &VMErrorKind::ExtensionFailed { name: String::from("test"), message: String::from("no") });
+
+    let error = run(extension(|context| context.pop().map(drop)), |b| b.extension(Opcode::new(ImpType::Arithmetic, "\t\n"))).run().unwrap_err();
+
+    assert_eq!(error.kind(), &VMErrorKind::StackUnderflow);
+}
+
+#[test]
+#[should_panic(expected = "extension clash overlaps a standard command")]
+fn extensions_cannot_overlap_standard_commands() {
+    Extensions::new().register("clash", Opcode::new(ImpType::Stack, "\t"), |_| Ok(()));
+}
+
+#[test]
+#[should_panic(expected = "extension clash overlaps extension debug_print_stack")]
+fn extensions_cannot_overlap_each_other() {
+    Extensions::debug().register("clash", Opcode::new(ImpType::IO, "\n"), |_| Ok(()));
+}
+
+#[test]
+#[should_panic(expected = "extension empty has an invalid pattern")]
+fn extensions_need_a_pattern() {
+    Extensions::new().register("empty", Opcode::new(ImpType::IO, ""), |_| Ok(()));
+}
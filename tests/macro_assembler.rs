@@ -0,0 +1,183 @@
+use whitespace::{AsmErrorKind, CommandType, MacroAssembler, Origin, Syntax};
+
+use std::fs;
+
+fn origin(line: usize) -> Origin {
+    Origin {
+        file: None,
+        line,
+    }
+}
+
+#[test]
+fn constants_replace_later_words() {
+    let commands = MacroAssembler::new().assemble("
+        .const SIZE 3
+        .const LAST SIZE
+        push SIZE
+        copy LAST
+    ");
+
+    assert_eq!(commands.unwrap(), [CommandType::Push(3.into()), CommandType::Copy(3.into())]);
+
+    let error = MacroAssembler::new().assemble(".const EMPTY").unwrap_err();
+
+    assert_eq!(error.kind(), &AsmErrorKind::MissingArgument { mnemonic: String::from(".const") });
+}
+
+#[test]
+fn local_labels_are_apart_in_each_call() {
+    let commands = MacroAssembler::new().assemble("
+        .macro skip n
+            jz @over
+            push n
+        label @over
+        .endm
+
+        skip 1
+        skip 2
+    ").unwrap();
+
+    assert_eq!(commands, [
+        CommandType::JumpZ(String::from("\t")),
+        CommandType::Push(1.into()),
+        CommandType::Labl(String::from("\t")),
+        CommandType::JumpZ(String::from("\t ")),
+        CommandType::Push(2.into()),
+        CommandType::Labl(String::from("\t ")),
+    ]);
+}
+
+#[test]
+fn local_labels_are_raw_in_the_reference_syntax() {
+    let commands = MacroAssembler::new().with_syntax(Syntax::Reference).assemble("
+        .macro skip
+            ifzero @over
+        label @over
+        .endm
+
+        label T
+        skip
+        skip
+        jump T
+    ").unwrap();
+
+    assert_eq!(commands, [
+        CommandType::Labl(String::from("\t")),
+        CommandType::JumpZ(String::from("\t ")),
+        CommandType::Labl(String::from("\t ")),
+        CommandType::JumpZ(String::from("\t\t")),
+        CommandType::Labl(String::from("\t\t")),
+        CommandType::Jump(String::from("\t")),
+    ]);
+}
+
+#[test]
+fn macros_are_called_with_as_many_arguments_as_they_have_parameters() {
+    let error = MacroAssembler::new().assemble("
+        .macro pair a, b
+            push a
+            push b
+        .endm
+
+        pair 1
+    ").unwrap_err();
+
+    assert_eq!(error.kind(), &AsmErrorKind::WrongArgumentCount {
+        name: String::from("pair"),
+        expected: 2,
+        found: 1,
+    });
+    assert_eq!(error.line(), 7);
+}
+
+#[test]
+fn macros_cannot_call_themselves() {
+    let error = MacroAssembler::new().assemble("
+        .macro forever
+            dup
+            forever
+        .endm
+
+        forever
+    ").unwrap_err();
+
+    assert_eq!(error.kind(), &AsmErrorKind::RecursiveMacro(String::from("forever")));
+    assert_eq!(error.line(), 4);
+    assert_eq!(error.call_sites(), [origin(7)]);
+}
+
+#[test]
+fn errors_in_macros_point_at_every_call() {
+    let error = MacroAssembler::new().assemble("
+        .macro inner
+            bogus
+        .endm
+
+        .macro outer
+            dup
+            inner
+        .endm
+
+        outer
+    ").unwrap_err();
+
+    assert_eq!(error.kind(), &AsmErrorKind::UnknownMnemonic(String::from("bogus")));
+    assert_eq!(error.origin(), &origin(3));
+    assert_eq!(error.call_sites(), [origin(8), origin(11)]);
+    assert_eq!(error.to_string(), "Unknown mnemonic \"bogus\" on line 3, in a macro called on line 8, in a macro called on line 11");
+}
+
+#[test]
+fn strings_are_printed_or_stored() {
+    let commands = MacroAssembler::new().assemble(r#".string "a,\n""#).unwrap();
+
+    assert_eq!(commands, [
+        CommandType::Push(('a' as u32).into()),
+        CommandType::OutC,
+        CommandType::Push((',' as u32).into()),
+        CommandType::OutC,
+        CommandType::Push(('\n' as u32).into()),
+        CommandType::OutC,
+    ]);
+
+    let commands = MacroAssembler::new().assemble(r#".string 10, "hi""#).unwrap();
+
+    assert_eq!(commands, [
+        CommandType::Push(10.into()),
+        CommandType::Push(('h' as u32).into()),
+        CommandType::Store,
+        CommandType::Push(11.into()),
+        CommandType::Push(('i' as u32).into()),
+        CommandType::Store,
+        CommandType::Push(12.into()),
+        CommandType::Push(0.into()),
+        CommandType::Store,
+    ]);
+
+    let error = MacroAssembler::new().assemble(".string hi").unwrap_err();
+
+    assert_eq!(error.kind(), &AsmErrorKind::InvalidString(String::from("hi")));
+}
+
+#[test]
+fn included_files_cannot_include_themselves() {
+    let directory = std::env::temp_dir().join(format!("whitespace-include-{}", std::process::id()));
+
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("a.wsa"), "dup\n.include \"b.wsa\"\n").unwrap();
+    fs::write(directory.join("b.wsa"), "swap\n.include \"a.wsa\"\n").unwrap();
+
+    let path = directory.join("a.wsa");
+    let source = fs::read_to_string(&path).unwrap();
+
+    let error = MacroAssembler::new().assemble_file(&source, &path).unwrap_err();
+
+    fs::remove_dir_all(&directory).unwrap();
+
+    assert!(matches!(error.kind(), AsmErrorKind::Include { message, .. } if message == "it is already being included"));
+    assert_eq!(error.origin(), &Origin {
+        file: Some(directory.join("b.wsa").display().to_string()),
+        line: 2,
+    });
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use common::{commands, program, source_in};
+use whitespace::{Alphabet, CommandType, Notation, Parser, ParserErrorKind, WhitespacePolicy};
+
+fn parse(source: &str, notation: Notation) -> Vec<CommandType> {
+    let output = Parser::new(String::from(source)).with_notation(notation).parse_all();
+
+    assert!(output.errors.is_empty(), "{:?}", output.errors);
+
+    commands(&output.tokens)
+}
+
+#[test]
+fn stl_source_is_read_as_s_t_and_l() {
+    assert_eq!(parse("SSS TSSTSSS L  push 72\nTLSS  outc\nLLL  end\n", Notation::Stl), program());
+}
+
+#[test]
+fn bracketed_source_is_read_between_brackets() {
+    let source = "\
+        [Space][Space][Space][Tab][Space][Space][Tab][Space][Space][Space][LF] push 72
+        [Tab][LF][Space][Space] [not a symbol] outc
+        [LF][LF][LF] end";
+
+    assert_eq!(parse(source, Notation::Bracketed), program());
+}
+
+#[test]
+fn unfinished_brackets_are_comments() {
+    assert_eq!(parse("[Tab][LF][Space][Space] [Tab [LF][LF][LF]\n[Tab", Notation::Bracketed), [CommandType::OutC, CommandType::EndP]);
+}
+
+#[test]
+fn whitespace_notation_keeps_the_alphabet() {
+    let alphabet = Alphabet::zero_width();
+
+    let source = source_in(&alphabet, &program());
+
+    let program_read = Parser::new(source)
+        .with_alphabet(alphabet)
+        .with_notation(Notation::Whitespace)
+        .parse()
+        .unwrap();
+
+    assert_eq!(program_read.commands().collect::<Vec<_>>(), program());
+}
+
+#[test]
+fn stl_notation_keeps_the_whitespace_policy() {
+    let output = Parser::new(String::from("TLSS TLSS"))
+        .with_alphabet(Alphabet::standard().with_other_whitespace(WhitespacePolicy::Error))
+        .with_notation(Notation::Stl)
+        .parse_all();
+
+    assert_eq!(commands(&output.tokens), [CommandType::OutC, CommandType::OutC]);
+    assert_eq!(output.errors.len(), 1);
+    assert_eq!(output.errors[0].kind(), &ParserErrorKind::UnexpectedWhitespace { character: ' ' });
+}
+
+#[test]
+fn commands_are_rendered_in_each_notation() {
+    assert_eq!(Notation::Whitespace.render_commands(&program()), "   \t  \t   \n\t\n  \n\n\n");
+    assert_eq!(Notation::Stl.render_commands(&program()), "SSSTSSTSSSL\nTLSS\nLLL");
+    assert_eq!(Notation::Bracketed.render_commands(&program()[1..]), "[Tab][LF][Space][Space]\n[LF][LF][LF]");
+}
+
+#[test]
+fn rendered_commands_parse_back() {
+    for notation in [Notation::Whitespace, Notation::Stl, Notation::Bracketed] {
+        assert_eq!(parse(&notation.render_commands(&program()), notation), program(), "{:?}", notation);
+    }
+}
+
+#[test]
+fn notations_are_named_like_the_command_line_names_them() {
+    assert_eq!(Notation::from_name("ws"), Some(Notation::Whitespace));
+    assert_eq!(Notation::from_name("stl"), Some(Notation::Stl));
+    assert_eq!(Notation::from_name("bracketed"), Some(Notation::Bracketed));
+    assert_eq!(Notation::from_name("STL"), None);
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use common::{commands, source};
+use whitespace::{CommandType, ImpType, Parser, ParserErrorKind, Token};
+
+use std::io::{self, Read};
+
+/// A source that can never be read.
+struct Failing;
+
+impl Read for Failing {
+    fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::other("the disk is on fire"))
+    }
+}
+
+/// A source that gives out a single byte on each read.
+struct Trickle<'a>(&'a [u8]);
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let length = buf.len().min(1);
+
+        self.0.read(&mut buf[..length])
+    }
+}
+
+#[test]
+fn parse_all_reports_every_error_with_the_tokens_around_them() {
+    let source = [
+        source(&[CommandType::Push(1.into())]),
+        String::from("\t \n"),
+        source(&[CommandType::Jump(String::from("\t"))]),
+        String::from("\t\t\n"),
+        source(&[CommandType::Labl(String::from(" ")), CommandType::Labl(String::from(" ")), CommandType::Dup]),
+    ].concat();
+
+    let output = Parser::new(source.clone()).parse_all();
+
+    assert_eq!(commands(&output.tokens), [
+        CommandType::Push(1.into()),
+        CommandType::Jump(String::from("\t")),
+        CommandType::Labl(String::from(" ")),
+        CommandType::Labl(String::from(" ")),
+        CommandType::Dup,
+    ]);
+
+    let kinds: Vec<&ParserErrorKind> = output.errors.iter().map(|error| error.kind()).collect();
+
+    assert_eq!(kinds, [
+        &ParserErrorKind::UnknownCommand { imp: ImpType::Arithmetic },
+        &ParserErrorKind::UndefinedLabel { label: String::from("\t") },
+        &ParserErrorKind::UnknownCommand { imp: ImpType::Heap },
+        &ParserErrorKind::DuplicateLabel { label: String::from(" "), previous: output.tokens[2].location },
+    ]);
+
+    assert!(output.errors.windows(2).all(|pair| pair[0].span().start < pair[1].span().start));
+    assert!(Parser::new(source).parse().is_err());
+}
+
+#[test]
+fn parse_all_of_a_valid_source_has_no_errors() {
+    let expected = [CommandType::Push(5.into()), CommandType::OutI, CommandType::EndP];
+
+    let output = Parser::new(source(&expected)).parse_all();
+
+    assert!(output.errors.is_empty());
+    assert_eq!(commands(&output.tokens), expected);
+}
+
+#[test]
+fn numbers_start_with_a_sign() {
+    let error = Parser::new(String::from("  \n\t\n")).next().unwrap().unwrap_err();
+
+    assert_eq!(error.kind(), &ParserErrorKind::MissingSign);
+    assert_eq!(error.expected(), [' ', '\t']);
+    assert_eq!(error.to_string(), "Number on line 1, column 3 did not start with a sign (expected space, tab)");
+}
+
+#[test]
+fn read_errors_are_reported() {
+    let error = Parser::from_reader(Failing).parse().unwrap_err();
+
+    assert_eq!(error.kind(), &ParserErrorKind::Io { message: String::from("the disk is on fire") });
+
+    let output = Parser::from_reader(Failing).parse_all();
+
+    assert!(output.tokens.is_empty());
+    assert_eq!(output.errors.len(), 1);
+    assert!(matches!(output.errors[0].kind(), ParserErrorKind::Io { .. }));
+
+    let results: Vec<_> = Parser::from_reader(Failing).collect();
+
+    assert!(matches!(results.as_slice(), [Err(error)] if matches!(error.kind(), ParserErrorKind::Io { .. })));
+}
+
+#[test]
+fn characters_split_across_reads_are_put_back_together() {
+    let source = [
+        source(&[CommandType::Push(5.into())]),
+        String::from("é€"),
+        source(&[CommandType::OutI]),
+        String::from("𝄞"),
+        source(&[CommandType::EndP]),
+    ].concat();
+
+    let expected: Vec<Token> = Parser::new(source.clone()).collect::<Result<_, _>>().unwrap();
+    let tokens: Vec<Token> = Parser::from_reader(Trickle(source.as_bytes())).collect::<Result<_, _>>().unwrap();
+
+    assert_eq!(tokens, expected);
+    assert_eq!(commands(&tokens), [CommandType::Push(5.into()), CommandType::OutI, CommandType::EndP]);
+    assert_eq!((tokens[1].location.start, tokens[1].location.line, tokens[1].location.column), (12, 2, 3));
+}
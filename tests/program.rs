@@ -0,0 +1,80 @@
+mod common;
+
+use common::source;
+use whitespace::{CommandType, ImpType, Instruction, Location, Parser, ParserErrorKind, Program};
+
+#[test]
+fn jumps_are_linked_to_the_index_of_their_label() {
+    let program = Parser::new(source(&[
+        CommandType::Jump(String::from("\t")),
+        CommandType::Labl(String::from(" ")),
+        CommandType::Call(String::from(" ")),
+        CommandType::Labl(String::from("\t")),
+        CommandType::JumpZ(String::from(" ")),
+        CommandType::JumpN(String::from("\t")),
+    ])).parse().unwrap();
+
+    assert_eq!(program.instructions(), [
+        Instruction::Jump(3),
+        Instruction::Labl(String::from(" ")),
+        Instruction::Call(1),
+        Instruction::Labl(String::from("\t")),
+        Instruction::JumpZ(1),
+        Instruction::JumpN(3),
+    ]);
+
+    assert_eq!(program.label("\t"), Some(3));
+    assert_eq!(program.label("\t\t"), None);
+}
+
+#[test]
+fn undefined_labels_are_rejected() {
+    let error = Parser::new(source(&[
+        CommandType::Dup,
+        CommandType::Call(String::from("\t ")),
+    ])).parse().unwrap_err();
+
+    assert_eq!(error.kind(), &ParserErrorKind::UndefinedLabel { label: String::from("\t ") });
+    assert_eq!(error.span(), &Location { start: 3, end: 9, line: 2, column: 2 });
+    assert_eq!(error.to_string(), "Label TS on line 2, column 2 is never defined");
+}
+
+#[test]
+fn duplicate_labels_point_at_both_definitions() {
+    let error = Parser::new(source(&[
+        CommandType::Labl(String::from(" ")),
+        CommandType::Dup,
+        CommandType::Labl(String::from(" ")),
+    ])).parse().unwrap_err();
+
+    assert_eq!(error.kind(), &ParserErrorKind::DuplicateLabel {
+        label: String::from(" "),
+        previous: Location { start: 0, end: 5, line: 1, column: 1 },
+    });
+    assert_eq!(error.span(), &Location { start: 8, end: 13, line: 4, column: 2 });
+}
+
+#[test]
+fn every_label_error_is_checked_in_source_order() {
+    let tokens: Vec<_> = Parser::new(source(&[
+        CommandType::Jump(String::from("\t")),
+        CommandType::Labl(String::from(" ")),
+        CommandType::Labl(String::from(" ")),
+    ])).collect::<Result<_, _>>().unwrap();
+
+    let errors = Program::check_labels(&tokens);
+
+    assert!(matches!(errors[0].kind(), ParserErrorKind::UndefinedLabel { .. }));
+    assert!(matches!(errors[1].kind(), ParserErrorKind::DuplicateLabel { .. }));
+    assert_eq!(errors.len(), 2);
+}
+
+#[test]
+fn separate_imp_tokens_cannot_be_linked() {
+    let tokens: Vec<_> = Parser::new(source(&[CommandType::Add])).lexemes().collect::<Result<_, _>>().unwrap();
+
+    let error = Program::link(tokens).unwrap_err();
+
+    assert_eq!(error.kind(), &ParserErrorKind::UnexpectedImp { imp: ImpType::Arithmetic });
+    assert_eq!(error.span().start, 0);
+}
@@ -0,0 +1,265 @@
+use whitespace::{encode, AsmErrorKind, Assembler, CommandType, Disassembler, Extensions, Parser, Syntax, VirtualMachine};
+
+use std::mem;
+
+fn assemble(syntax: Syntax, source: &str) -> Vec<CommandType> {
+    Assembler::new().with_syntax(syntax).assemble(source).unwrap()
+}
+
+/// Every command with placeholder arguments, in the order of the tables.
+fn every_command() -> Vec<CommandType> {
+    vec![
+        CommandType::Push(0.into()), CommandType::Dup, CommandType::Copy(0.into()), CommandType::Swap, CommandType::Disc, CommandType::Slide(0.into()),
+        CommandType::Add, CommandType::Sub, CommandType::Mult, CommandType::IDiv, CommandType::Mod, CommandType::Store, CommandType::Retr,
+        CommandType::Labl(String::from("")), CommandType::Call(String::from("")), CommandType::Jump(String::from("")), CommandType::JumpZ(String::from("")), CommandType::JumpN(String::from("")), CommandType::EndS, CommandType::EndP,
+        CommandType::OutC, CommandType::OutI, CommandType::ReadC, CommandType::ReadI,
+    ]
+}
+
+#[test]
+fn wsa_mnemonics_are_imported() {
+    let commands = assemble(Syntax::Wsa, "
+        label loop
+        push 'a'    ; a character
+        push ';'
+        doub
+        pop
+        retrive
+        retrieve
+        jumpz loop
+        jumpn loop
+        outC
+        outN
+        inC
+        inN
+        exit
+    ");
+
+    assert_eq!(commands, [
+        CommandType::Labl(String::from("\t")),
+        CommandType::Push(97.into()),
+        CommandType::Push(59.into()),
+        CommandType::Dup,
+        CommandType::Disc,
+        CommandType::Retr,
+        CommandType::Retr,
+        CommandType::JumpZ(String::from("\t")),
+        CommandType::JumpN(String::from("\t")),
+        CommandType::OutC,
+        CommandType::OutI,
+        CommandType::ReadC,
+        CommandType::ReadI,
+        CommandType::EndP,
+    ]);
+}
+
+#[test]
+fn wsa_arithmetic_and_heap_access_take_a_number() {
+    let commands = assemble(Syntax::Wsa, "
+        add 5
+        SUB 1
+        mul 2
+        div 3
+        mod 4
+        store 1
+        retrive 0
+    ");
+
+    let push = |n: i32| CommandType::Push(n.into());
+
+    assert_eq!(commands, [
+        push(5), CommandType::Add,
+        push(1), CommandType::Sub,
+        push(2), CommandType::Mult,
+        push(3), CommandType::IDiv,
+        push(4), CommandType::Mod,
+        push(1), CommandType::Swap, CommandType::Store,
+        push(0), CommandType::Retr,
+    ]);
+
+    let output = run_wsa("push 7\nstore 1\nretrive 1\nadd 2\noutN\nexit");
+
+    assert_eq!(output, "9");
+
+    let error = Assembler::new().assemble("add 5").unwrap_err();
+
+    assert_eq!(error.kind(), &AsmErrorKind::UnexpectedArgument { mnemonic: String::from("add") });
+}
+
+/// Runs wsa `source` and gives back what it printed.
+fn run_wsa(source: &str) -> String {
+    let whitespace = Assembler::new().with_syntax(Syntax::Wsa).assemble_to_whitespace(source).unwrap();
+    let program = Parser::new(whitespace).parse().unwrap();
+
+    let mut virtual_machine = VirtualMachine::with_io(program, &b""[..], Vec::new());
+
+    virtual_machine.run().unwrap();
+
+    String::from_utf8(virtual_machine.output().clone()).unwrap()
+}
+
+#[test]
+fn wsa_conditional_jumps_test_the_top_of_the_stack() {
+    let cases = [
+        ("jumpp", [false, false, true]),
+        ("jumpnp", [true, false, true]),
+        ("jumppn", [true, false, true]),
+        ("jumpnz", [true, true, false]),
+        ("jumpzn", [true, true, false]),
+        ("jumppz", [false, true, true]),
+        ("jumpzp", [false, true, true]),
+    ];
+
+    for (jump, expected) in cases {
+        for (value, jumps) in [-2, 0, 2].into_iter().zip(expected) {
+            // The 9 underneath shows that only the tested value is taken off
+            let source = format!("push 9\npush {}\n{} yes\npush 0\noutN\nlabel yes\noutN\nexit", value, jump);
+
+            assert_eq!(run_wsa(&source), if jumps {"9"} else {"09"}, "{} {}", jump, value);
+        }
+    }
+
+    let error = Assembler::new().with_syntax(Syntax::Wsa).assemble("jumpp").unwrap_err();
+
+    assert_eq!(error.kind(), &AsmErrorKind::MissingArgument { mnemonic: String::from("jumpp") });
+}
+
+#[test]
+fn wsa_debugging_extensions_are_spelled_without_the_last_underscore() {
+    let extensions = Extensions::debug();
+
+    let commands = Assembler::new().with_syntax(Syntax::Wsa).with_extensions(&extensions).assemble("
+        debug_printstack
+        debug_printheap
+        debug_print_stack
+    ").unwrap();
+
+    let opcodes: Vec<_> = extensions.iter().map(|extension| CommandType::Extension(extension.opcode.clone())).collect();
+
+    assert_eq!(commands, [opcodes[0].clone(), opcodes[1].clone(), opcodes[0].clone()]);
+
+    let error = Assembler::new().with_extensions(&extensions).assemble("debug_printstack").unwrap_err();
+
+    assert_eq!(error.kind(), &AsmErrorKind::UnknownMnemonic(String::from("debug_printstack")));
+}
+
+#[test]
+fn character_literals_are_only_wsa() {
+    let error = Assembler::new().assemble("push 'a'").unwrap_err();
+
+    assert_eq!(error.kind(), &AsmErrorKind::InvalidNumber(String::from("'a'")));
+
+    let kind = |source: &str| Assembler::new().with_syntax(Syntax::Wsa).assemble(source).unwrap_err().kind().clone();
+
+    assert_eq!(kind("push 'ab'"), AsmErrorKind::InvalidNumber(String::from("'ab'")));
+    assert_eq!(kind("push ''"), AsmErrorKind::InvalidNumber(String::from("''")));
+}
+
+#[test]
+fn reference_mnemonics_are_imported_with_raw_labels() {
+    let commands = assemble(Syntax::Reference, "
+        label STS
+        label %
+        ref 1
+        plus
+        minus
+        times
+        divide
+        modulo
+        ifzero %
+        ifnegative STS
+        call %STS
+        return
+        outputchar
+        outputnum
+        readchar
+        readnum
+        end
+    ");
+
+    assert_eq!(commands, [
+        CommandType::Labl(String::from(" \t ")),
+        CommandType::Labl(String::from("")),
+        CommandType::Copy(1.into()),
+        CommandType::Add,
+        CommandType::Sub,
+        CommandType::Mult,
+        CommandType::IDiv,
+        CommandType::Mod,
+        CommandType::JumpZ(String::from("")),
+        CommandType::JumpN(String::from(" \t ")),
+        CommandType::Call(String::from(" \t ")),
+        CommandType::EndS,
+        CommandType::OutC,
+        CommandType::OutI,
+        CommandType::ReadC,
+        CommandType::ReadI,
+        CommandType::EndP,
+    ]);
+}
+
+#[test]
+fn reference_labels_cannot_be_named() {
+    let error = Assembler::new().with_syntax(Syntax::Reference).assemble("label loop").unwrap_err();
+
+    assert_eq!(error.kind(), &AsmErrorKind::InvalidLabel(String::from("loop")));
+}
+
+#[test]
+fn every_syntax_has_a_mnemonic_for_each_command() {
+    let expected = [
+        (Syntax::Native, ["push", "dup", "copy", "swap", "drop", "slide", "add", "sub", "mul", "div", "mod", "store", "retr", "label", "call", "jump", "jz", "jn", "ret", "end", "outc", "outi", "readc", "readi"]),
+        (Syntax::Wsa, ["push", "doub", "copy", "swap", "pop", "slide", "add", "sub", "mul", "div", "mod", "store", "retrive", "label", "call", "jump", "jumpz", "jumpn", "ret", "exit", "outC", "outN", "inC", "inN"]),
+        (Syntax::Reference, ["push", "dup", "ref", "swap", "discard", "slide", "plus", "minus", "times", "divide", "modulo", "store", "retrieve", "label", "call", "jump", "ifzero", "ifnegative", "return", "end", "outputchar", "outputnum", "readchar", "readnum"]),
+    ];
+
+    for (syntax, mnemonics) in expected {
+        let exported: Vec<&str> = every_command().iter().map(|command| syntax.mnemonic(command).unwrap()).collect();
+
+        assert_eq!(exported, mnemonics);
+
+        for (mnemonic, command) in mnemonics.iter().zip(every_command()) {
+            assert_eq!(mem::discriminant(syntax.command(&mnemonic.to_uppercase()).unwrap()), mem::discriminant(&command));
+        }
+    }
+}
+
+#[test]
+fn programs_are_exported_and_imported_in_each_syntax() {
+    let source = "
+        push 3
+        label loop
+        dup
+        jz done
+        dup
+        outi
+        push 1
+        sub
+        jump loop
+        label done
+        end
+    ";
+
+    let commands = assemble(Syntax::Native, source);
+    let program = Parser::new(commands.iter().map(encode).collect()).parse().unwrap();
+
+    for syntax in [Syntax::Native, Syntax::Wsa, Syntax::Reference] {
+        let exported = Disassembler::new().with_syntax(syntax).disassemble(&program);
+
+        assert_eq!(assemble(syntax, &exported), commands, "{:?}:\n{}", syntax, exported);
+    }
+
+    let wsa = Disassembler::new().with_syntax(Syntax::Wsa).disassemble(&program);
+    let reference = Disassembler::new().with_syntax(Syntax::Reference).disassemble(&program);
+
+    assert!(wsa.contains("    doub ") && wsa.contains("    jumpz label_1 ") && wsa.contains("    outN "));
+    assert!(reference.contains("label T ") && reference.contains("    ifzero TS ") && reference.contains("    minus "));
+}
+
+#[test]
+fn syntaxes_are_named_like_the_command_line_names_them() {
+    assert_eq!(Syntax::from_name("native"), Some(Syntax::Native));
+    assert_eq!(Syntax::from_name("wsa"), Some(Syntax::Wsa));
+    assert_eq!(Syntax::from_name("reference"), Some(Syntax::Reference));
+    assert_eq!(Syntax::from_name("nasm"), None);
+}
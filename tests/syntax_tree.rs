@@ -0,0 +1,84 @@
+mod common;
+
+use common::{commands, source};
+use whitespace::{Alphabet, CommandType, Notation, SyntaxTree, Token};
+
+fn tree_commands(tree: &SyntaxTree) -> Vec<CommandType> {
+    commands(&tree.tokens().cloned().collect::<Vec<Token>>())
+}
+
+#[test]
+fn comments_inside_instructions_stay_in_their_text() {
+    let source = "push: |  \t#one\n\t\nout \t\n\n\n";
+
+    let tree = SyntaxTree::parse(source).unwrap();
+
+    assert_eq!(tree.to_string(), source);
+    assert_eq!(tree_commands(&tree), [CommandType::Push(1.into()), CommandType::OutI, CommandType::EndP]);
+
+    assert_eq!(tree.nodes[0].leading_trivia, "push:");
+    assert_eq!(tree.nodes[0].text, " |  \t#one\n");
+    assert_eq!(tree.nodes[1].text, "\t\nout \t");
+    assert_eq!(tree.trailing_trivia, "");
+}
+
+#[test]
+fn multi_byte_characters_are_kept() {
+    let source = ["é", &source(&[CommandType::Push(2.into())]), "→\t\n😀  ", "ü"].concat();
+
+    let tree = SyntaxTree::parse(&source).unwrap();
+
+    assert_eq!(tree.to_string(), source);
+    assert_eq!(tree.nodes[0].leading_trivia, "é");
+    assert_eq!(tree.nodes[1].leading_trivia, "→");
+    assert_eq!(tree.nodes[1].text, "\t\n😀  ");
+    assert_eq!(tree_commands(&tree), [CommandType::Push(2.into()), CommandType::OutC]);
+    assert_eq!(tree.trailing_trivia, "ü");
+}
+
+#[test]
+fn crlf_line_endings_are_kept() {
+    let source = "   \t\r\n\t\r\n \t\r\n\r\n\r\n#done\r";
+
+    let tree = SyntaxTree::parse(source).unwrap();
+
+    assert_eq!(tree.to_string(), source);
+    assert_eq!(tree_commands(&tree), [CommandType::Push(1.into()), CommandType::OutI, CommandType::EndP]);
+    assert_eq!(tree.nodes[2].leading_trivia, "\r");
+    assert_eq!(tree.nodes[2].text, "\n\r\n\r\n");
+    assert_eq!(tree.trailing_trivia, "#done\r");
+}
+
+#[test]
+fn trailing_trivia_holds_everything_after_the_last_instruction() {
+    let source = [&source(&[CommandType::EndP]), "THE-END"].concat();
+
+    let tree = SyntaxTree::parse(&source).unwrap();
+
+    assert_eq!(tree.to_string(), source);
+    assert_eq!(tree.nodes.len(), 1);
+    assert_eq!(tree.trailing_trivia, "THE-END");
+
+    let tree = SyntaxTree::parse("only-a-comment").unwrap();
+
+    assert!(tree.nodes.is_empty());
+    assert_eq!(tree.to_string(), "only-a-comment");
+}
+
+#[test]
+fn trees_can_be_parsed_with_a_configured_parser() {
+    let source = "SSSTL TLST LLL // prints 1";
+
+    let tree = SyntaxTree::parse_with(source, |parser| parser.with_notation(Notation::Stl)).unwrap();
+
+    assert_eq!(tree.to_string(), source);
+    assert_eq!(tree_commands(&tree), [CommandType::Push(1.into()), CommandType::OutI, CommandType::EndP]);
+    assert_eq!(tree.trailing_trivia, " // prints 1");
+
+    let alphabet = Alphabet::new('a', 'b', 'c');
+
+    let tree = SyntaxTree::parse_with("xaaabc!", |parser| parser.with_alphabet(alphabet)).unwrap();
+
+    assert_eq!(tree.to_string(), "xaaabc!");
+    assert_eq!(tree_commands(&tree), [CommandType::Push(1.into())]);
+}
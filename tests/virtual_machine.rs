@@ -0,0 +1,445 @@
+use whitespace::{encode, CommandType, Dialect, ImpType, Instruction, Limit, Limits, Location, NumericMode, Parser, ParserErrorKind, Program, ProgramBuilder, Status, Value, VMError, VirtualMachine, VMErrorKind};
+
+use std::io::{self, Read};
+use std::sync::Arc;
+use std::thread;
+
+fn build(f: impl FnOnce(&mut ProgramBuilder) -> &mut ProgramBuilder) -> Program {
+    let mut builder = ProgramBuilder::new();
+
+    f(&mut builder);
+
+    builder.build().unwrap()
+}
+
+fn machine(program: Program, input: &[u8]) -> VirtualMachine<&[u8], Vec<u8>> {
+    VirtualMachine::with_io(program, input, Vec::new())
+}
+
+/// Input that has nothing to read yet the first time it is asked.
+struct Blocking<'a> {
+    blocked: bool,
+    input: &'a [u8],
+}
+
+impl Read for Blocking<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.blocked {
+            self.blocked = false;
+
+            return Err(io::ErrorKind::WouldBlock.into());
+        };
+
+        self.input.read(buf)
+    }
+}
+
+#[test]
+fn a_jump_loop_runs_out_of_fuel() {
+    let mut virtual_machine = machine(build(|b| b.label("loop").jump("loop")), b"");
+
+    virtual_machine.set_fuel(Some(100));
+
+    assert_eq!(virtual_machine.run(), Ok(Status::OutOfFuel));
+    assert_eq!(virtual_machine.status(), Status::OutOfFuel);
+    assert_eq!(virtual_machine.fuel(), Some(0));
+}
+
+#[test]
+fn adding_fuel_resumes_where_it_stopped() {
+    let mut virtual_machine = machine(build(|b| b.push(1).out_int().push(2).out_int().end()), b"");
+
+    virtual_machine.set_fuel(Some(2));
+
+    assert_eq!(virtual_machine.run(), Ok(Status::OutOfFuel));
+    assert_eq!(virtual_machine.output(), b"1");
+    assert_eq!(virtual_machine.pointer(), 2);
+
+    virtual_machine.add_fuel(10);
+
+    assert_eq!(virtual_machine.run(), Ok(Status::Halted));
+    assert_eq!(virtual_machine.output(), b"12");
+    assert_eq!(virtual_machine.fuel(), Some(7));
+}
+
+#[test]
+fn waiting_for_input_does_not_use_fuel() {
+    let program = build(|b| b.push(0).read_char().end());
+
+    let mut virtual_machine = VirtualMachine::with_io(program, Blocking { blocked: true, input: b"a" }, Vec::new());
+
+    virtual_machine.set_fuel(Some(3));
+
+    assert_eq!(virtual_machine.run(), Ok(Status::WaitingForInput));
+    assert_eq!(virtual_machine.fuel(), Some(2));
+
+    assert_eq!(virtual_machine.run(), Ok(Status::Halted));
+    assert_eq!(virtual_machine.fuel(), Some(0));
+    assert_eq!(virtual_machine.heap().get(&0.into()), Some(&97.into()));
+}
+
+#[test]
+fn the_stack_limit_stops_runaway_pushes() {
+    let mut virtual_machine = machine(build(|b| b.label("loop").push(1).jump("loop")), b"");
+
+    virtual_machine.set_limits(Limits { max_stack: Some(3), ..Limits::default() });
+
+    let error = virtual_machine.run().unwrap_err();
+
+    assert_eq!(error.kind(), &VMErrorKind::LimitExceeded { limit: Limit::Stack, max: 3 });
+    assert_eq!(error.to_string(), "Exceeded the stack size limit of 3 at instruction 1 on line 3, column 1");
+    assert_eq!(virtual_machine.stack().len(), 3);
+    assert_eq!(virtual_machine.status(), Status::Errored);
+}
+
+#[test]
+fn the_heap_limit_counts_distinct_cells() {
+    let mut virtual_machine = machine(build(|b| b
+        .push(0).push(1).store()
+        .push(0).push(2).store()
+        .push(1).push(3).store()
+        .end()
+    ), b"");
+
+    virtual_machine.set_limits(Limits { max_heap: Some(1), ..Limits::default() });
+
+    let error = virtual_machine.run().unwrap_err();
+
+    assert_eq!(error.kind(), &VMErrorKind::LimitExceeded { limit: Limit::Heap, max: 1 });
+    assert_eq!(error.pointer(), 8);
+    assert_eq!(virtual_machine.heap().get(&0.into()), Some(&2.into()));
+}
+
+#[test]
+fn the_call_depth_limit_stops_runaway_recursion() {
+    let mut virtual_machine = machine(build(|b| b.label("recurse").call("recurse")), b"");
+
+    virtual_machine.set_limits(Limits { max_call_depth: Some(10), ..Limits::default() });
+
+    let error = virtual_machine.run().unwrap_err();
+
+    assert_eq!(error.kind(), &VMErrorKind::LimitExceeded { limit: Limit::CallDepth, max: 10 });
+    assert_eq!(error.call_stack().len(), 10);
+}
+
+#[test]
+fn machines_are_unlimited_by_default() {
+    let mut virtual_machine = machine(build(|b| b.label("loop").push(1).jump("loop")), b"");
+
+    assert_eq!(virtual_machine.limits(), &Limits::default());
+    assert_eq!(virtual_machine.run_for(30_000), Ok(Status::Running));
+    assert_eq!(virtual_machine.stack().len(), 10_000);
+}
+
+#[test]
+fn literals_must_fit_the_numeric_mode() {
+    let parse = |n: Value, numeric_mode: NumericMode| Parser::new(encode(&CommandType::Push(n)))
+        .with_numeric_mode(numeric_mode)
+        .parse();
+
+    let above_i32: Value = Value::from(i32::MAX) + 1;
+    let above_i64: Value = Value::from(i64::MAX) + 1;
+
+    assert!(parse(Value::from(i32::MIN), NumericMode::Checked).is_ok());
+    assert_eq!(parse(above_i32.clone(), NumericMode::Checked).unwrap_err().kind(), &ParserErrorKind::NumberOutOfRange { mode: NumericMode::Checked });
+    assert_eq!(parse(above_i32.clone(), NumericMode::Wrapping).unwrap_err().kind(), &ParserErrorKind::NumberOutOfRange { mode: NumericMode::Wrapping });
+
+    assert!(parse(above_i32, NumericMode::I64).is_ok());
+    assert_eq!(parse(above_i64.clone(), NumericMode::I64).unwrap_err().kind(), &ParserErrorKind::NumberOutOfRange { mode: NumericMode::I64 });
+
+    assert!(parse(above_i64 << 64, NumericMode::BigInt).is_ok());
+}
+
+#[test]
+fn arithmetic_follows_the_numeric_mode() {
+    let run = |numeric_mode: NumericMode| {
+        let mut virtual_machine = machine(build(|b| b.push(i32::MAX).push(1).add().end()), b"")
+            .with_numeric_mode(numeric_mode);
+
+        virtual_machine.run().map(|_| virtual_machine.stack().to_vec())
+    };
+
+    let error = run(NumericMode::Checked).unwrap_err();
+
+    assert_eq!(error.kind(), &VMErrorKind::Overflow { value: Value::from(i32::MAX) + 1, mode: NumericMode::Checked });
+    assert_eq!(error.pointer(), 2);
+
+    assert_eq!(run(NumericMode::Wrapping), Ok(vec![Value::from(i32::MIN)]));
+    assert_eq!(run(NumericMode::I64), Ok(vec![Value::from(i32::MAX) + 1]));
+    assert_eq!(run(NumericMode::BigInt), Ok(vec![Value::from(i32::MAX) + 1]));
+}
+
+#[test]
+fn i64_overflows_where_bigint_does_not() {
+    let run = |numeric_mode: NumericMode| {
+        let mut virtual_machine = machine(build(|b| b.push(i64::MAX).dup().mul().end()), b"")
+            .with_numeric_mode(numeric_mode);
+
+        virtual_machine.run().map(|_| virtual_machine.stack().to_vec())
+    };
+
+    assert!(matches!(run(NumericMode::I64).unwrap_err().kind(), VMErrorKind::Overflow { mode: NumericMode::I64, .. }));
+    assert_eq!(run(NumericMode::BigInt), Ok(vec![Value::from(i64::MAX) * i64::MAX]));
+}
+
+#[test]
+fn read_numbers_must_fit_the_numeric_mode() {
+    let run = |numeric_mode: NumericMode, input: &'static [u8]| {
+        let mut virtual_machine = machine(build(|b| b.push(0).read_int().end()), input)
+            .with_numeric_mode(numeric_mode);
+
+        virtual_machine.run().map(|_| virtual_machine.heap()[&Value::from(0)].clone())
+    };
+
+    assert_eq!(run(NumericMode::Checked, b"-2147483648\n"), Ok(Value::from(i32::MIN)));
+    assert_eq!(run(NumericMode::Checked, b"2147483648\n").unwrap_err().kind(), &VMErrorKind::InvalidNumber(String::from("2147483648")));
+    assert!(run(NumericMode::Wrapping, b"2147483648\n").is_err());
+    assert_eq!(run(NumericMode::I64, b"2147483648\n"), Ok(Value::from(i32::MAX) + 1));
+    assert_eq!(run(NumericMode::BigInt, b"99999999999999999999\n"), Ok("99999999999999999999".parse().unwrap()));
+}
+
+#[test]
+fn whitespace_02_has_no_copy_or_slide() {
+    for command in [CommandType::Copy(0.into()), CommandType::Slide(1.into())] {
+        let parse = |dialect: Dialect| Parser::new(encode(&command)).with_dialect(dialect).parse();
+
+        assert_eq!(parse(Dialect::Whitespace02).unwrap_err().kind(), &ParserErrorKind::UnknownCommand { imp: ImpType::Stack });
+        assert!(parse(Dialect::Whitespace03).is_ok());
+        assert!(parse(Dialect::Permissive).is_ok());
+    }
+}
+
+#[test]
+fn strict_reads_pop_their_address() {
+    for (dialect, stack) in [(Dialect::Permissive, vec![Value::from(0), Value::from(1)]), (Dialect::Whitespace03, vec![])] {
+        let mut virtual_machine = machine(build(|b| b.push(0).read_char().push(1).read_int().end()), b"a7\n")
+            .with_dialect(dialect);
+
+        assert_eq!(virtual_machine.run(), Ok(Status::Halted));
+        assert_eq!(virtual_machine.stack(), stack);
+        assert_eq!(virtual_machine.heap()[&Value::from(0)], Value::from(97));
+        assert_eq!(virtual_machine.heap()[&Value::from(1)], Value::from(7));
+    }
+}
+
+#[test]
+fn strict_division_rounds_towards_negative_infinity() {
+    let run = |dialect: Dialect| {
+        let mut virtual_machine = machine(build(|b| b
+            .push(-7).push(2).div()
+            .push(-7).push(2).modulo()
+            .push(7).push(-2).div()
+            .push(7).push(-2).modulo()
+            .end()
+        ), b"").with_dialect(dialect);
+
+        virtual_machine.run().unwrap();
+
+        virtual_machine.stack().to_vec()
+    };
+
+    let values = |values: [i32; 4]| values.map(Value::from).to_vec();
+
+    assert_eq!(run(Dialect::Permissive), values([-3, -1, -3, 1]));
+    assert_eq!(run(Dialect::Whitespace03), values([-4, 1, -4, -1]));
+}
+
+#[test]
+fn strict_programs_must_end() {
+    let program = build(|b| b.push(1).discard());
+
+    let mut virtual_machine = machine(program.clone(), b"").with_dialect(Dialect::Whitespace03);
+
+    let error = virtual_machine.run().unwrap_err();
+
+    assert_eq!(error.kind(), &VMErrorKind::MissingEnd);
+    assert_eq!(error.pointer(), 2);
+    assert_eq!(virtual_machine.status(), Status::Errored);
+
+    assert_eq!(machine(program, b"").run(), Ok(Status::Halted));
+}
+
+#[test]
+fn returning_without_a_call_moves_on_or_fails() {
+    let program = build(|b| b.ret().push(1).out_int().end());
+
+    let mut virtual_machine = machine(program.clone(), b"");
+
+    assert_eq!(virtual_machine.run_for(1), Ok(Status::Running));
+    assert_eq!(virtual_machine.pointer(), 1);
+    assert_eq!(virtual_machine.run(), Ok(Status::Halted));
+    assert_eq!(virtual_machine.output(), b"1");
+
+    let mut virtual_machine = machine(program, b"").with_dialect(Dialect::Whitespace02);
+
+    assert_eq!(virtual_machine.run().unwrap_err().kind(), &VMErrorKind::ReturnOutsideSubroutine);
+}
+
+#[test]
+fn dialects_are_named_like_the_command_line_names_them() {
+    assert_eq!(Dialect::from_name("permissive"), Some(Dialect::Permissive));
+    assert_eq!(Dialect::from_name("0.2"), Some(Dialect::Whitespace02));
+    assert_eq!(Dialect::from_name("0.3"), Some(Dialect::Whitespace03));
+    assert_eq!(Dialect::from_name("strict"), None);
+}
+
+/// The error a program stops with.
+fn failure(f: impl FnOnce(&mut ProgramBuilder) -> &mut ProgramBuilder) -> VMError {
+    machine(build(f), b"").run().unwrap_err()
+}
+
+fn values<const N: usize>(values: [i64; N]) -> Vec<Value> {
+    values.map(Value::from).to_vec()
+}
+
+#[test]
+fn errors_keep_the_operands_that_caused_them() {
+    let error = failure(|b| b.push(7).add());
+
+    assert_eq!(error.kind(), &VMErrorKind::StackUnderflow);
+    assert_eq!(error.stack_top(), values([7]));
+
+    assert_eq!(failure(|b| b.push(1).push(2).push(3).slide(5)).stack_top(), values([1, 2, 3]));
+    assert_eq!(failure(|b| b.push(4).push(0).div()).stack_top(), values([4, 0]));
+    assert_eq!(failure(|b| b.push(4).push(0).modulo()).stack_top(), values([4, 0]));
+    assert_eq!(failure(|b| b.push(-1).push(5).store()).stack_top(), values([-1, 5]));
+    assert_eq!(failure(|b| b.push(-1).retrieve()).stack_top(), values([-1]));
+    assert_eq!(failure(|b| b.push(-1).out_char()).stack_top(), values([-1]));
+
+    let error = machine(build(|b| b.push(i64::MAX).push(2).mul()), b"")
+        .with_numeric_mode(NumericMode::I64)
+        .run()
+        .unwrap_err();
+
+    assert!(matches!(error.kind(), VMErrorKind::Overflow { .. }));
+    assert_eq!(error.stack_top(), values([i64::MAX, 2]));
+}
+
+#[test]
+fn errors_keep_only_the_top_of_the_stack() {
+    let error = failure(|b| (1..=9).fold(b, |b, n| b.push(n)).push(0).div());
+
+    assert_eq!(error.stack_top(), values([3, 4, 5, 6, 7, 8, 9, 0]));
+}
+
+#[test]
+fn errors_point_at_the_failing_instruction_in_the_source() {
+    let error = failure(|b| b.push(7).dup().add().add());
+
+    assert_eq!(error.pointer(), 3);
+    assert_eq!(error.location(), Some(&Location { start: 14, end: 18, line: 3, column: 6 }));
+    assert_eq!(error.to_string(), "Stack does not hold enough values at instruction 3 on line 3, column 6");
+
+    let error = machine(build(|b| b.push(1)), b"").with_dialect(Dialect::Whitespace03).run().unwrap_err();
+
+    assert_eq!(error.kind(), &VMErrorKind::MissingEnd);
+    assert_eq!(error.location(), None);
+}
+
+#[test]
+fn machines_share_one_program() {
+    let program = Arc::new(build(|b| b.push(0).read_char().retrieve().out_char().end()));
+
+    let mut first = VirtualMachine::with_io(Arc::clone(&program), &b"a"[..], Vec::new());
+    let mut second = VirtualMachine::with_io(Arc::clone(&program), &b"b"[..], Vec::new());
+
+    assert!(Arc::ptr_eq(first.program(), second.program()));
+    assert_eq!(Arc::strong_count(&program), 3);
+
+    first.set_fuel(Some(2));
+
+    assert_eq!(first.run(), Ok(Status::OutOfFuel));
+    assert_eq!(second.run(), Ok(Status::Halted));
+
+    first.set_fuel(None);
+
+    assert_eq!(first.run(), Ok(Status::Halted));
+    assert_eq!(first.output(), b"a");
+    assert_eq!(second.output(), b"b");
+}
+
+#[test]
+fn machines_run_on_other_threads() {
+    let program = Arc::new(build(|b| b.push(6).push(7).mul().out_int().end()));
+
+    let threads: Vec<_> = (0..4).map(|_| {
+        let mut virtual_machine = VirtualMachine::with_io(Arc::clone(&program), io::empty(), Vec::new());
+
+        thread::spawn(move || {
+            virtual_machine.run().unwrap();
+
+            virtual_machine.into_io().1
+        })
+    }).collect();
+
+    for thread in threads {
+        assert_eq!(thread.join().unwrap(), b"42");
+    }
+}
+
+#[test]
+fn resetting_runs_the_program_again() {
+    let mut virtual_machine = machine(build(|b| b.push(0).push(1).store().push(5).out_int().end()), b"");
+
+    assert_eq!(virtual_machine.run(), Ok(Status::Halted));
+
+    virtual_machine.reset();
+
+    assert_eq!(virtual_machine.status(), Status::Running);
+    assert_eq!(virtual_machine.pointer(), 0);
+    assert!(virtual_machine.stack().is_empty());
+    assert!(virtual_machine.heap().is_empty());
+
+    assert_eq!(virtual_machine.run(), Ok(Status::Halted));
+    assert_eq!(virtual_machine.heap().get(&Value::from(0)), Some(&Value::from(1)));
+    assert_eq!(virtual_machine.output(), b"55");
+}
+
+#[test]
+fn errored_machines_run_again_after_a_reset() {
+    let mut virtual_machine = machine(build(|b| b.push(1).out_int().add()), b"");
+
+    assert!(virtual_machine.run().is_err());
+
+    virtual_machine.reset();
+
+    assert_eq!(virtual_machine.run().unwrap_err().kind(), &VMErrorKind::StackUnderflow);
+    assert_eq!(virtual_machine.output(), b"11");
+}
+
+#[test]
+fn stepping_a_stopped_machine_keeps_its_status() {
+    let mut virtual_machine = machine(build(|b| b.push(1).end()), b"");
+
+    assert_eq!(virtual_machine.step(), Ok(Status::Running));
+    assert_eq!(virtual_machine.step(), Ok(Status::Halted));
+    assert_eq!(virtual_machine.step(), Ok(Status::Halted));
+    assert_eq!(virtual_machine.pointer(), 1);
+
+    let mut virtual_machine = machine(build(|b| b.add().end()), b"");
+
+    assert_eq!(virtual_machine.step().unwrap_err().kind(), &VMErrorKind::StackUnderflow);
+    assert_eq!(virtual_machine.step(), Ok(Status::Errored));
+    assert_eq!(virtual_machine.status(), Status::Errored);
+    assert_eq!(virtual_machine.pointer(), 0);
+}
+
+#[test]
+fn running_until_stops_before_the_matching_instruction() {
+    let mut virtual_machine = machine(build(|b| b.push(1).push(2).add().out_int().end()), b"");
+
+    let is_add = |virtual_machine: &VirtualMachine<&[u8], Vec<u8>>| {
+        matches!(virtual_machine.program().instructions().get(virtual_machine.pointer()), Some(Instruction::Add))
+    };
+
+    assert_eq!(virtual_machine.run_until(is_add), Ok(Status::Running));
+    assert_eq!(virtual_machine.pointer(), 2);
+    assert_eq!(virtual_machine.stack(), values([1, 2]));
+
+    // A predicate that already holds does not step at all
+    assert_eq!(virtual_machine.run_until(is_add), Ok(Status::Running));
+    assert_eq!(virtual_machine.pointer(), 2);
+
+    assert_eq!(virtual_machine.run_until(|_| false), Ok(Status::Halted));
+    assert_eq!(virtual_machine.output(), b"3");
+}